@@ -1,46 +1,124 @@
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
-pub struct Index(libixx::Index);
+pub struct Index(libixx::IndexView<'static>);
 
 #[wasm_bindgen]
 pub struct SearchedOption {
   idx: usize,
-  scope_id: u8,
+  scope_id: u32,
   name: String,
+  score: u32,
+  matches: Vec<MatchRange>,
+}
+
+/// The results of a search on one page, see [`Index::search_page`].
+#[wasm_bindgen]
+pub struct SearchedPage {
+  options: Vec<SearchedOption>,
+  total: usize,
+}
+
+#[wasm_bindgen]
+pub struct Child(libixx::Child);
+
+#[wasm_bindgen]
+pub struct Entry {
+  idx: usize,
+  scope_id: u32,
+  name: String,
+}
+
+#[wasm_bindgen]
+pub struct Metadata(libixx::Metadata);
+
+/// Part of a result name which matched the query, in UTF-16 code units like JavaScript strings.
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct MatchRange {
+  pub start: usize,
+  pub end: usize,
 }
 
 #[wasm_bindgen]
 impl Index {
   pub fn read(buf: Vec<u8>) -> Result<Self, String> {
-    libixx::Index::read(&buf)
+    libixx::IndexView::read(&buf)
       .map(Self)
       .map_err(|err| format!("{err:?}"))
   }
 
   pub fn search(
     &self,
-    scope_id: Option<u8>,
+    scope_id: Option<u32>,
     #[wasm_bindgen(unchecked_param_type = "string")] query: &JsValue,
     max_results: usize,
+    fuzzy: Option<bool>,
+    case_sensitive: Option<bool>,
   ) -> Result<Vec<SearchedOption>, String> {
     let query_str = query
       .as_string()
       .ok_or_else(|| "Invalid query: expected a string".to_string())?;
-    match self.0.search(scope_id, &query_str, max_results) {
-      Ok(options) => Ok(
-        options
-          .into_iter()
-          .map(|(idx, scope_id, name)| SearchedOption { idx, scope_id, name })
-          .collect(),
-      ),
+    let options = libixx::SearchOptions {
+      fuzzy: fuzzy.unwrap_or(false),
+      case_sensitive: case_sensitive.unwrap_or(false),
+    };
+    match self.0.search_with(scope_id, &query_str, max_results, &options) {
+      Ok(options) => Ok(options.into_iter().map(SearchedOption::from).collect()),
+      Err(err) => Err(format!("{err:?}")),
+    }
+  }
+
+  /// Like `search`, but skips the best `offset` results and counts all options matching the query.
+  pub fn search_page(
+    &self,
+    scope_id: Option<u32>,
+    #[wasm_bindgen(unchecked_param_type = "string")] query: &JsValue,
+    offset: usize,
+    max_results: usize,
+    fuzzy: Option<bool>,
+    case_sensitive: Option<bool>,
+  ) -> Result<SearchedPage, String> {
+    let query_str = query
+      .as_string()
+      .ok_or_else(|| "Invalid query: expected a string".to_string())?;
+    let options = libixx::SearchOptions {
+      fuzzy: fuzzy.unwrap_or(false),
+      case_sensitive: case_sensitive.unwrap_or(false),
+    };
+    match self
+      .0
+      .search_page(scope_id, &query_str, offset, max_results, &options)
+    {
+      Ok(page) => Ok(SearchedPage {
+        options: page.hits.into_iter().map(SearchedOption::from).collect(),
+        total: page.total,
+      }),
       Err(err) => Err(format!("{err:?}")),
     }
   }
 
+  /// Proposes existing names close to the query, for when searching finds nothing, only in `scope_id`
+  /// if it is given.
+  pub fn suggest(
+    &self,
+    scope_id: Option<u32>,
+    #[wasm_bindgen(unchecked_param_type = "string")] query: &JsValue,
+    max_results: usize,
+  ) -> Result<Vec<String>, String> {
+    let query_str = query
+      .as_string()
+      .ok_or_else(|| "Invalid query: expected a string".to_string())?;
+
+    self
+      .0
+      .suggest(scope_id, &query_str, max_results)
+      .map_err(|err| format!("{err:?}"))
+  }
+
   pub fn get_idx_by_name(
     &self,
-    scope_id: u8,
+    scope_id: u32,
     #[wasm_bindgen(unchecked_param_type = "string")] name: &JsValue,
   ) -> Result<Option<usize>, String> {
     let name_str = name
@@ -50,10 +128,186 @@ impl Index {
     Ok(self.0.get_idx_by_name(scope_id, &name_str))
   }
 
+  /// Resolves a path from a configuration, like `services.nginx.virtualHosts.example.root`, to the
+  /// option declaring it.
+  pub fn get_idx_by_config_path(
+    &self,
+    scope_id: u32,
+    #[wasm_bindgen(unchecked_param_type = "string")] path: &JsValue,
+  ) -> Result<Option<usize>, String> {
+    let path_str = path
+      .as_string()
+      .ok_or_else(|| "Invalid path: expected a string".to_string())?;
+
+    Ok(self.0.get_idx_by_config_path(scope_id, &path_str))
+  }
+
+  /// Name of the option at `idx`, e.g. to render breadcrumbs for an option loaded from a chunk.
+  #[must_use]
+  pub fn name_of(&self, idx: usize) -> Option<String> {
+    self.0.name_of(idx)
+  }
+
+  #[must_use]
+  pub fn scope_of(&self, idx: usize) -> Option<u32> {
+    self.0.scope_of(idx)
+  }
+
+  /// Every option of the index, in index order.
+  #[must_use]
+  pub fn entries(&self) -> Vec<Entry> {
+    self
+      .0
+      .entries()
+      .map(|(idx, scope_id, name)| Entry { idx, scope_id, name })
+      .collect()
+  }
+
+  /// Lists the labels directly below an option path like `services.nginx`, or the first labels of all
+  /// options if it is empty, to browse the options as a tree.
+  pub fn children(
+    &self,
+    scope_id: u32,
+    #[wasm_bindgen(unchecked_param_type = "string")] prefix: &JsValue,
+  ) -> Result<Vec<Child>, String> {
+    let prefix_str = prefix
+      .as_string()
+      .ok_or_else(|| "Invalid prefix: expected a string".to_string())?;
+
+    Ok(
+      self
+        .0
+        .children(scope_id, &prefix_str)
+        .into_iter()
+        .map(Child)
+        .collect(),
+    )
+  }
+
+  /// Completes the last label of a partially typed option path like `services.ngi`, most frequent
+  /// labels first.
+  pub fn complete(
+    &self,
+    scope_id: u32,
+    #[wasm_bindgen(unchecked_param_type = "string")] partial: &JsValue,
+    max_results: usize,
+  ) -> Result<Vec<String>, String> {
+    let partial_str = partial
+      .as_string()
+      .ok_or_else(|| "Invalid path: expected a string".to_string())?;
+
+    Ok(self.0.complete(scope_id, &partial_str, max_results))
+  }
+
   #[must_use]
   pub fn size(&self) -> usize {
     self.0.size()
   }
+
+  #[must_use]
+  pub fn metadata(&self) -> Metadata {
+    Metadata(self.0.metadata().clone())
+  }
+}
+
+#[wasm_bindgen]
+impl Metadata {
+  #[must_use]
+  pub fn format_version(&self) -> u8 {
+    self.0.format_version
+  }
+
+  /// Number of entries per chunk file, `0` if unknown.
+  #[must_use]
+  pub fn chunk_size(&self) -> u32 {
+    self.0.chunk_size
+  }
+
+  /// Either `options`, `packages` or `unknown`.
+  #[must_use]
+  pub fn kind(&self) -> String {
+    match self.0.kind {
+      libixx::EntityKind::Unknown => "unknown",
+      libixx::EntityKind::Options => "options",
+      libixx::EntityKind::Packages => "packages",
+    }
+    .to_string()
+  }
+
+  /// Seconds since the unix epoch, `0` if unknown.
+  #[must_use]
+  pub fn build_timestamp(&self) -> u64 {
+    self.0.build_timestamp
+  }
+
+  #[must_use]
+  pub fn generator(&self) -> String {
+    self.0.generator.clone()
+  }
+
+  /// Scope names, indexed by scope id.
+  #[must_use]
+  pub fn scopes(&self) -> Vec<String> {
+    self.0.scopes.clone()
+  }
+}
+
+#[wasm_bindgen]
+impl SearchedPage {
+  #[must_use]
+  pub fn options(self) -> Vec<SearchedOption> {
+    self.options
+  }
+
+  /// Number of options matching the query, on any page.
+  #[must_use]
+  pub fn total(&self) -> usize {
+    self.total
+  }
+}
+
+#[wasm_bindgen]
+impl Entry {
+  #[must_use]
+  pub fn idx(&self) -> usize {
+    self.idx
+  }
+
+  #[must_use]
+  pub fn scope_id(&self) -> u32 {
+    self.scope_id
+  }
+
+  #[must_use]
+  pub fn name(self) -> String {
+    self.name
+  }
+}
+
+#[wasm_bindgen]
+impl Child {
+  #[must_use]
+  pub fn label(&self) -> String {
+    self.0.label.clone()
+  }
+
+  /// The prefix and this label as an option path, which can be passed to `children` again.
+  #[must_use]
+  pub fn path(&self) -> String {
+    self.0.path.clone()
+  }
+
+  /// Number of options below the prefix and this label, including the one named by them.
+  #[must_use]
+  pub fn entries(&self) -> usize {
+    self.0.entries
+  }
+
+  /// Index of the option named by the prefix and this label, if there is one.
+  #[must_use]
+  pub fn idx(&self) -> Option<usize> {
+    self.0.idx
+  }
 }
 
 #[wasm_bindgen]
@@ -64,12 +318,46 @@ impl SearchedOption {
   }
 
   #[must_use]
-  pub fn scope_id(&self) -> u8 {
+  pub fn scope_id(&self) -> u32 {
     self.scope_id
   }
 
+  /// Relevance of this result, higher is better. Results are already sorted by it.
+  #[must_use]
+  pub fn score(&self) -> u32 {
+    self.score
+  }
+
+  #[must_use]
+  pub fn matches(&self) -> Vec<MatchRange> {
+    self.matches.clone()
+  }
+
   #[must_use]
   pub fn name(self) -> String {
     self.name
   }
 }
+
+impl From<libixx::SearchHit> for SearchedOption {
+  fn from(hit: libixx::SearchHit) -> Self {
+    Self {
+      idx: hit.idx,
+      scope_id: hit.scope_id,
+      matches: hit
+        .matches
+        .iter()
+        .map(|range| MatchRange {
+          start: utf16_len(&hit.name[..range.start]),
+          end: utf16_len(&hit.name[..range.end]),
+        })
+        .collect(),
+      name: hit.name,
+      score: hit.score.value(),
+    }
+  }
+}
+
+fn utf16_len(str: &str) -> usize {
+  str.encode_utf16().count()
+}
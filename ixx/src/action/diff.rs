@@ -0,0 +1,239 @@
+use std::{
+  collections::{HashMap, hash_map},
+  fs::File,
+  path::{Path, PathBuf},
+};
+
+use anyhow::{Context, bail};
+use libixx::{Index, IxxError};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::args::{DiffModule, Format};
+
+/// Fields of chunk entries compared between both builds.
+const COMPARED_FIELDS: [&str; 3] = ["type", "default", "description"];
+
+#[derive(Serialize, Debug, Default)]
+struct Diff {
+  added: Vec<Entry>,
+  removed: Vec<Entry>,
+  moved: Vec<Moved>,
+  changed: Vec<Changed>,
+}
+
+#[derive(Serialize, Debug)]
+struct Entry {
+  name: String,
+  scope_id: u32,
+  scope: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+struct Moved {
+  name: String,
+  old_scope_id: u32,
+  old_scope: Option<String>,
+  new_scope_id: u32,
+  new_scope: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+struct Changed {
+  name: String,
+  scope_id: u32,
+  scope: Option<String>,
+  fields: Vec<&'static str>,
+}
+
+pub(crate) fn diff(module: DiffModule) -> anyhow::Result<()> {
+  let old = read_index(&module.old)?;
+  let new = read_index(&module.new)?;
+  let index_diff = old.diff(&new);
+
+  let mut diff = Diff {
+    added: index_diff
+      .added
+      .into_iter()
+      .map(|entry| Entry {
+        name: entry.name,
+        scope_id: entry.scope_id,
+        scope: scope_name(&new, entry.scope_id),
+      })
+      .collect(),
+    removed: index_diff
+      .removed
+      .into_iter()
+      .map(|entry| Entry {
+        name: entry.name,
+        scope_id: entry.scope_id,
+        scope: scope_name(&old, entry.scope_id),
+      })
+      .collect(),
+    moved: Vec::new(),
+    changed: Vec::new(),
+  };
+
+  let mut chunks = match (module.old_chunks, module.new_chunks) {
+    (Some(old_chunks), Some(new_chunks)) => {
+      Some((Chunks::new(&old, old_chunks)?, Chunks::new(&new, new_chunks)?))
+    }
+    _ => None,
+  };
+
+  let mut compare = |old_idx: usize, new_idx: usize| -> anyhow::Result<Vec<&'static str>> {
+    let Some((old_chunks, new_chunks)) = &mut chunks else {
+      return Ok(Vec::new());
+    };
+    let old_entry = old_chunks.entry(old_idx)?;
+    let new_entry = new_chunks.entry(new_idx)?;
+
+    Ok(
+      COMPARED_FIELDS
+        .into_iter()
+        .filter(|field| old_entry.get(field) != new_entry.get(field))
+        .collect(),
+    )
+  };
+
+  for moved in index_diff.moved {
+    let fields = compare(moved.old_idx, moved.new_idx)?;
+    if !fields.is_empty() {
+      diff.changed.push(Changed {
+        name: moved.name.clone(),
+        scope_id: moved.new_scope_id,
+        scope: scope_name(&new, moved.new_scope_id),
+        fields,
+      });
+    }
+
+    diff.moved.push(Moved {
+      name: moved.name,
+      old_scope_id: moved.old_scope_id,
+      old_scope: scope_name(&old, moved.old_scope_id),
+      new_scope_id: moved.new_scope_id,
+      new_scope: scope_name(&new, moved.new_scope_id),
+    });
+  }
+
+  for (old_idx, new_idx) in index_diff.unchanged {
+    let fields = compare(old_idx, new_idx)?;
+    if !fields.is_empty() {
+      let scope_id = new
+        .scope_of(new_idx)
+        .ok_or(IxxError::MalformedIndex("entry out of range"))?;
+      let name = new.name_of(new_idx).ok_or(IxxError::MalformedIndex(
+        "entry with labels missing from the label table",
+      ))?;
+      diff.changed.push(Changed {
+        name,
+        scope_id,
+        scope: scope_name(&new, scope_id),
+        fields,
+      });
+    }
+  }
+  diff.changed.sort_by(|a, b| a.name.cmp(&b.name));
+
+  match module.format {
+    Format::Json => {
+      let json_output = serde_json::to_string_pretty(&diff)?;
+      println!("{json_output}");
+    }
+    Format::Text => {
+      for entry in diff.added {
+        println!(
+          "added: {}, {}",
+          entry.name,
+          display_scope(entry.scope_id, entry.scope.as_deref())
+        );
+      }
+      for entry in diff.removed {
+        println!(
+          "removed: {}, {}",
+          entry.name,
+          display_scope(entry.scope_id, entry.scope.as_deref())
+        );
+      }
+      for entry in diff.moved {
+        println!(
+          "moved: {}, {} -> {}",
+          entry.name,
+          display_scope(entry.old_scope_id, entry.old_scope.as_deref()),
+          display_scope(entry.new_scope_id, entry.new_scope.as_deref())
+        );
+      }
+      for entry in diff.changed {
+        println!(
+          "changed: {}, {}, fields: {}",
+          entry.name,
+          display_scope(entry.scope_id, entry.scope.as_deref()),
+          entry.fields.join(", ")
+        );
+      }
+    }
+  }
+
+  Ok(())
+}
+
+/// Name of the scope in the metadata of `index`, `ixx02` indexes have none.
+fn scope_name(index: &Index, scope_id: u32) -> Option<String> {
+  index.metadata().scope_name(scope_id).map(str::to_owned)
+}
+
+/// Scope like `ixx meta` prints it.
+fn display_scope(scope_id: u32, scope: Option<&str>) -> String {
+  match scope {
+    Some(scope) => format!("scope {scope_id}: {scope}"),
+    None => format!("scope {scope_id}"),
+  }
+}
+
+fn read_index(path: &Path) -> anyhow::Result<Index> {
+  let mut file = File::open(path).with_context(|| format!("Failed to open {}", path.to_string_lossy()))?;
+  Ok(Index::read_from(&mut file)?)
+}
+
+/// Chunk files of one build, read on demand.
+struct Chunks {
+  dir: PathBuf,
+  chunk_size: usize,
+  loaded: HashMap<usize, Vec<Value>>,
+}
+
+impl Chunks {
+  fn new(index: &Index, dir: PathBuf) -> anyhow::Result<Self> {
+    let chunk_size = index.metadata().chunk_size as usize;
+    if chunk_size == 0 {
+      bail!("The index does not record its chunk size, rebuild it to read chunks");
+    }
+
+    Ok(Self {
+      dir,
+      chunk_size,
+      loaded: HashMap::new(),
+    })
+  }
+
+  fn entry(&mut self, idx: usize) -> anyhow::Result<&Value> {
+    let chunk = idx / self.chunk_size;
+    let path = self.dir.join(format!("{chunk}.json"));
+
+    let entries = match self.loaded.entry(chunk) {
+      hash_map::Entry::Occupied(entry) => entry.into_mut(),
+      hash_map::Entry::Vacant(entry) => {
+        let raw_chunk = std::fs::read_to_string(&path)
+          .with_context(|| format!("Failed to read chunk {}", path.to_string_lossy()))?;
+        entry.insert(
+          serde_json::from_str(&raw_chunk)
+            .with_context(|| format!("Failed to parse chunk {}", path.to_string_lossy()))?,
+        )
+      }
+    };
+
+    entries
+      .get(idx % self.chunk_size)
+      .with_context(|| format!("Chunk {} is missing entry {idx}", path.to_string_lossy()))
+  }
+}
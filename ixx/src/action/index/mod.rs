@@ -1,7 +1,11 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+  collections::HashMap,
+  path::PathBuf,
+  time::{SystemTime, UNIX_EPOCH},
+};
 
 use anyhow::Context;
-use libixx::License;
+use libixx::{EntityKind, License, Metadata};
 use serde::{Deserialize, Serialize};
 use tokio::{fs::File, io::AsyncWriteExt, join};
 use url::Url;
@@ -24,6 +28,7 @@ pub(crate) struct Config {
 #[derive(Debug, Deserialize)]
 #[serde(deny_unknown_fields, rename_all = "camelCase")]
 pub(crate) struct Scope {
+  name: Option<String>,
   license_mapping: HashMap<String, License>,
   maintainer_mapping: HashMap<u32, Maintainer>,
   team_mapping: HashMap<String, Team>,
@@ -35,13 +40,13 @@ pub(crate) struct Scope {
 
 struct OptionEntry {
   name: String,
-  scope: u8,
+  scope: u32,
   option: libixx::Option,
 }
 
 struct PackageEntry {
   name: String,
-  scope: u8,
+  scope: u32,
   package: libixx::Package,
 }
 
@@ -64,7 +69,7 @@ struct Team {
 #[derive(Serialize)]
 #[serde(deny_unknown_fields, rename_all = "camelCase")]
 struct Meta {
-  scopes: HashMap<u8, ScopeMeta>,
+  scopes: HashMap<u32, ScopeMeta>,
 }
 
 #[derive(Clone, Serialize)]
@@ -90,16 +95,16 @@ pub(crate) async fn index(module: IndexModule) -> anyhow::Result<()> {
       .iter()
       .enumerate()
       .map(|(idx, scope)| {
-        (
-          idx as u8,
+        Ok((
+          scope_id(idx)?,
           ScopeMeta {
             licenses: scope.license_mapping.clone(),
             maintainers: scope.maintainer_mapping.clone(),
             teams: scope.team_mapping.clone(),
           },
-        )
+        ))
       })
-      .collect(),
+      .collect::<anyhow::Result<_>>()?,
   };
 
   let (options_result, packages_result) = join!(
@@ -123,6 +128,32 @@ pub(crate) async fn index(module: IndexModule) -> anyhow::Result<()> {
   Ok(())
 }
 
+/// Scope ids are the position of the scope in the config.
+fn scope_id(scope_idx: usize) -> anyhow::Result<u32> {
+  u32::try_from(scope_idx).with_context(|| format!("Too many scopes, at most {} are supported", u32::MAX))
+}
+
+fn metadata(module: &IndexModule, config: &Config, kind: EntityKind) -> anyhow::Result<Metadata> {
+  // honour reproducible builds, e.g. inside of nix
+  let build_timestamp = match std::env::var("SOURCE_DATE_EPOCH") {
+    Ok(epoch) => epoch.parse().context("Failed to parse SOURCE_DATE_EPOCH")?,
+    Err(_) => SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+  };
+
+  Ok(Metadata {
+    chunk_size: module.chunk_size,
+    kind,
+    build_timestamp,
+    generator: concat!("ixx ", env!("CARGO_PKG_VERSION")).to_string(),
+    scopes: config
+      .scopes
+      .iter()
+      .map(|scope| scope.name.clone().unwrap_or_default())
+      .collect(),
+    ..Metadata::default()
+  })
+}
+
 fn update_declaration(url_prefix: &Url, declaration: Declaration) -> anyhow::Result<Url> {
   let mut url = match declaration {
     Declaration::StorePath(path) => {
@@ -1,12 +1,12 @@
 use std::{collections::HashMap, io::Cursor};
 
 use anyhow::Context;
-use libixx::Index;
+use libixx::{EntityKind, Index};
 use tokio::{fs::File, io::AsyncWriteExt, task::JoinSet};
 use url::Url;
 
 use crate::{
-  action::index::{Config, OptionEntry, update_declaration},
+  action::index::{Config, OptionEntry, metadata, scope_id, update_declaration},
   args::IndexModule,
   option::{self, Content},
 };
@@ -15,6 +15,7 @@ pub(crate) async fn index_options(module: &IndexModule, config: &Config) -> anyh
   let mut raw_options: Vec<OptionEntry> = vec![];
 
   for (scope_idx, scope) in config.scopes.iter().enumerate() {
+    let scope_id = scope_id(scope_idx)?;
     let options_json = match &scope.options_json {
       Some(options_jsons) => options_jsons,
       None => {
@@ -52,7 +53,7 @@ pub(crate) async fn index_options(module: &IndexModule, config: &Config) -> anyh
 
       raw_options.push(OptionEntry {
         name,
-        scope: scope_idx as u8,
+        scope: scope_id,
         option,
       });
     }
@@ -73,7 +74,8 @@ pub(crate) async fn index_options(module: &IndexModule, config: &Config) -> anyh
       .map(|entry| (entry.name.as_str(), entry.scope))
       .collect::<Vec<_>>()
       .as_slice(),
-  );
+  )?
+  .with_metadata(metadata(module, config, EntityKind::Options)?);
 
   println!(
     "Writing options index to {}",
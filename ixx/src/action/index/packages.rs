@@ -5,13 +5,13 @@ use std::{
 };
 
 use anyhow::Context;
-use libixx::Index;
+use libixx::{EntityKind, Index};
 use regex::{Captures, Regex};
 use tokio::{fs::File, io::AsyncWriteExt, task::JoinSet};
 use url::Url;
 
 use crate::{
-  action::index::{Config, License, Meta, PackageEntry, ScopeMeta, update_declaration},
+  action::index::{Config, License, Meta, PackageEntry, ScopeMeta, metadata, scope_id, update_declaration},
   args::IndexModule,
   package::{self, OneOrMany},
 };
@@ -20,11 +20,12 @@ pub(crate) async fn index_packages(
   module: &IndexModule,
   meta: &Meta,
   config: &Config,
-) -> anyhow::Result<HashMap<u8, HashMap<String, License>>> {
+) -> anyhow::Result<HashMap<u32, HashMap<String, License>>> {
   let mut raw_packages = Vec::<PackageEntry>::new();
-  let mut all_extra_licenses = HashMap::<u8, HashMap<String, License>>::new();
+  let mut all_extra_licenses = HashMap::<u32, HashMap<String, License>>::new();
 
   for (scope_idx, scope) in config.scopes.iter().enumerate() {
+    let scope_id = scope_id(scope_idx)?;
     let packages_jsons = match &scope.packages_jsons {
       Some(packages_jsons) => packages_jsons,
       None => {
@@ -37,7 +38,7 @@ pub(crate) async fn index_packages(
     let url_prefix = Arc::new(scope.url_prefix.clone());
 
     for packages_json in packages_jsons {
-      let scope_meta = meta.scopes[&(scope_idx as u8)].clone();
+      let scope_meta = meta.scopes[&scope_id].clone();
       let packages_json = packages_json.clone();
       let url_prefix = url_prefix.clone();
 
@@ -66,7 +67,7 @@ pub(crate) async fn index_packages(
             extra_licenses.extend(extras);
             Ok::<_, anyhow::Error>(PackageEntry {
               name: pkg.attr_name.clone(),
-              scope: scope_idx as u8,
+              scope: scope_id,
               package: pkg,
             })
           })
@@ -78,7 +79,7 @@ pub(crate) async fn index_packages(
       while let Some(result) = join_set.join_next().await {
         let (pkgs, extras) = result??;
         raw_packages.extend(pkgs);
-        if let Some(val) = all_extra_licenses.get_mut(&(scope_idx as u8)) {
+        if let Some(val) = all_extra_licenses.get_mut(&scope_id) {
           val.extend(extras);
         };
       }
@@ -99,7 +100,8 @@ pub(crate) async fn index_packages(
       .map(|entry| (entry.name.as_str(), entry.scope))
       .collect::<Vec<_>>()
       .as_slice(),
-  );
+  )?
+  .with_metadata(metadata(module, config, EntityKind::Packages)?);
 
   println!(
     "Writing packages index to {}",
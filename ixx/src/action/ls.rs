@@ -0,0 +1,46 @@
+use std::fs::File;
+
+use libixx::Index;
+use serde::Serialize;
+
+use crate::args::{Format, LsModule};
+
+#[derive(Serialize, Debug)]
+struct Entry {
+  label: String,
+  path: String,
+  entries: usize,
+  idx: Option<usize>,
+}
+
+pub(crate) fn ls(module: LsModule) -> anyhow::Result<()> {
+  let mut file = File::open(module.index)?;
+  let index = Index::read_from(&mut file)?;
+
+  let children = index.children(module.scope_id, &module.prefix);
+
+  match module.format {
+    Format::Json => {
+      let entries: Vec<Entry> = children
+        .into_iter()
+        .map(|child| Entry {
+          label: child.label,
+          path: child.path,
+          entries: child.entries,
+          idx: child.idx,
+        })
+        .collect();
+
+      let json_output = serde_json::to_string_pretty(&entries)?;
+      println!("{json_output}");
+    }
+    Format::Text => {
+      for child in children {
+        let idx = child.idx.map_or_else(|| "-".to_string(), |idx| idx.to_string());
+        println!("idx: {idx}, entries: {}, name: {}", child.entries, child.path);
+      }
+    }
+  }
+
+  Ok(())
+}
@@ -0,0 +1,31 @@
+use std::fs::File;
+
+use libixx::Index;
+
+use crate::args::{Format, MetaModule};
+
+pub(crate) fn meta(module: MetaModule) -> anyhow::Result<()> {
+  let mut file = File::open(module.index)?;
+  let index = Index::read_from(&mut file)?;
+  let metadata = index.metadata();
+
+  match module.format {
+    Format::Json => {
+      let json_output = serde_json::to_string_pretty(metadata)?;
+      println!("{json_output}");
+    }
+    Format::Text => {
+      println!("format version: {}", metadata.format_version);
+      println!("kind: {:?}", metadata.kind);
+      println!("chunk size: {}", metadata.chunk_size);
+      println!("build timestamp: {}", metadata.build_timestamp);
+      println!("generator: {}", metadata.generator);
+      println!("entries: {}", index.size());
+      for (scope_id, name) in metadata.scopes.iter().enumerate() {
+        println!("scope {scope_id}: {name}");
+      }
+    }
+  }
+
+  Ok(())
+}
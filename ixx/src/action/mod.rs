@@ -1,2 +1,6 @@
+pub(crate) mod diff;
 pub(crate) mod index;
+pub(crate) mod ls;
+pub(crate) mod meta;
 pub(crate) mod search;
+pub(crate) mod show;
@@ -1,6 +1,10 @@
-use std::fs::File;
+use std::{
+  fs::File,
+  io::{IsTerminal, stdout},
+  ops::Range,
+};
 
-use libixx::Index;
+use libixx::{Index, SearchOptions};
 use serde::{Deserialize, Serialize};
 
 use crate::args::{Format, SearchModule};
@@ -8,32 +12,122 @@ use crate::args::{Format, SearchModule};
 #[derive(Serialize, Deserialize, Debug)]
 struct Entry {
   idx: usize,
-  scope_id: u8,
+  scope_id: u32,
   name: String,
+  score: u32,
+  matches: Vec<Range<usize>>,
 }
 
 pub(crate) fn search(module: SearchModule) -> anyhow::Result<()> {
   let mut file = File::open(module.index)?;
   let index = Index::read_from(&mut file)?;
 
-  let result = index.search(module.scope_id, &module.query, module.max_results as usize)?;
+  let page = if module.regex {
+    index.search_regex_page(
+      module.scope_id,
+      &module.query,
+      module.offset as usize,
+      module.max_results as usize,
+    )?
+  } else {
+    let options = SearchOptions {
+      fuzzy: module.fuzzy,
+      case_sensitive: module.case_sensitive,
+    };
+    index.search_page(
+      module.scope_id,
+      &module.query,
+      module.offset as usize,
+      module.max_results as usize,
+      &options,
+    )?
+  };
+  let total = page.total;
+  let result = page.hits;
+
+  // keep stdout parsable for JSON, where an empty list already says that nothing was found
+  if total == 0 && !module.regex {
+    let suggestions = index.suggest(module.scope_id, &module.query, 5)?;
+    if !suggestions.is_empty() {
+      eprintln!("No results, did you mean:");
+      for suggestion in suggestions {
+        eprintln!("  {suggestion}");
+      }
+    }
+  }
 
   match module.format {
     Format::Json => {
       let entries: Vec<Entry> = result
         .into_iter()
-        .map(|(idx, scope_id, name)| Entry { idx, scope_id, name })
+        .map(|hit| Entry {
+          idx: hit.idx,
+          scope_id: hit.scope_id,
+          name: hit.name,
+          score: hit.score.value(),
+          matches: hit.matches,
+        })
         .collect();
 
       let json_output = serde_json::to_string_pretty(&entries)?;
       println!("{json_output}");
     }
     Format::Text => {
-      for (idx, scope_id, name) in result {
-        println!("idx: {idx}, scope_id: {scope_id}, name: {name}");
+      let bold = stdout().is_terminal();
+      let shown = module.offset as usize + 1..=module.offset as usize + result.len();
+
+      for hit in result {
+        let name = if bold {
+          embolden(&hit.name, &hit.matches)
+        } else {
+          hit.name
+        };
+
+        println!(
+          "idx: {}, scope_id: {}, score: {}, name: {name}",
+          hit.idx,
+          hit.scope_id,
+          hit.score.value(),
+        );
+      }
+
+      if !shown.is_empty() {
+        println!("showing {}-{} of {total} results", shown.start(), shown.end());
+      } else if total != 0 {
+        println!("no results past the first {total}");
       }
     }
   }
 
   Ok(())
 }
+
+fn embolden(name: &str, matches: &[Range<usize>]) -> String {
+  let mut result = String::with_capacity(name.len() + matches.len() * 8);
+  let mut last = 0;
+
+  for range in matches {
+    result.push_str(&name[last..range.start]);
+    result.push_str("\x1b[1m");
+    result.push_str(&name[range.clone()]);
+    result.push_str("\x1b[0m");
+    last = range.end;
+  }
+
+  result.push_str(&name[last..]);
+  result
+}
+
+#[cfg(test)]
+mod test {
+  use crate::action::search::embolden;
+
+  #[test]
+  fn test_embolden() {
+    assert_eq!(
+      embolden("home.enableNixpkgsReleaseCheck", &[0..2, 11..14]),
+      "\x1b[1mho\x1b[0mme.enable\x1b[1mNix\x1b[0mpkgsReleaseCheck"
+    );
+    assert_eq!(embolden("pretalx", &[]), "pretalx");
+  }
+}
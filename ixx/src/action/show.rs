@@ -0,0 +1,75 @@
+use std::fs::File;
+
+use anyhow::{Context, bail};
+use libixx::Index;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::args::{Format, ShowModule};
+
+#[derive(Serialize)]
+struct Declaration {
+  idx: usize,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  chunk: Option<usize>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  entry: Option<Value>,
+}
+
+pub(crate) fn show(module: ShowModule) -> anyhow::Result<()> {
+  let mut file = File::open(&module.index)?;
+  let index = Index::read_from(&mut file)?;
+
+  let Some(idx) = index.get_idx_by_config_path(module.scope_id, &module.path) else {
+    bail!("Nothing in scope {} declares {}", module.scope_id, module.path);
+  };
+
+  let chunk_size = index.metadata().chunk_size as usize;
+  let chunk = (chunk_size != 0).then(|| idx / chunk_size);
+
+  let entry = match &module.chunks {
+    Some(chunks) => {
+      let Some(chunk) = chunk else {
+        bail!("The index does not record its chunk size, rebuild it to read chunks");
+      };
+
+      let path = chunks.join(format!("{chunk}.json"));
+      let raw_chunk = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read chunk {}", path.to_string_lossy()))?;
+      let mut entries: Vec<Value> = serde_json::from_str(&raw_chunk)
+        .with_context(|| format!("Failed to parse chunk {}", path.to_string_lossy()))?;
+
+      let position = idx % chunk_size;
+      if position >= entries.len() {
+        bail!("Chunk {} is missing entry {idx}", path.to_string_lossy());
+      }
+      Some(entries.swap_remove(position))
+    }
+    None => None,
+  };
+
+  let declaration = Declaration { idx, chunk, entry };
+
+  match module.format {
+    Format::Json => {
+      let json_output = serde_json::to_string_pretty(&declaration)?;
+      println!("{json_output}");
+    }
+    Format::Text => {
+      println!("idx: {}", declaration.idx);
+      if let Some(chunk) = declaration.chunk {
+        println!("chunk: {chunk}");
+      }
+      if let Some(Value::Object(entry)) = declaration.entry {
+        for (key, value) in entry {
+          match value {
+            Value::String(value) => println!("{key}: {value}"),
+            value => println!("{key}: {value}"),
+          }
+        }
+      }
+    }
+  }
+
+  Ok(())
+}
@@ -1,6 +1,9 @@
 use clap::{Parser, Subcommand, ValueEnum};
+use clap_complete::ArgValueCompleter;
 use std::path::PathBuf;
 
+use crate::completion::complete_name;
+
 #[derive(Parser)]
 pub(super) struct Args {
   #[clap(subcommand)]
@@ -13,6 +16,14 @@ pub(super) enum Action {
   Index(IndexModule),
   #[clap(about = "Search the index for packages or options")]
   Search(SearchModule),
+  #[clap(about = "Show the metadata of an index")]
+  Meta(MetaModule),
+  #[clap(about = "Show the option declaring a path from a configuration")]
+  Show(ShowModule),
+  #[clap(about = "List the labels directly below a prefix of the names")]
+  Ls(LsModule),
+  #[clap(about = "Show the entries added, removed or changed between two builds of an index")]
+  Diff(DiffModule),
 }
 
 #[derive(ValueEnum, Clone)]
@@ -46,17 +57,56 @@ pub(super) struct IndexModule {
 
 #[derive(Parser)]
 pub(super) struct SearchModule {
+  /// whitespace separated terms, which all have to match, with `-term` to exclude, `a|b` for
+  /// alternatives, `=label` for whole labels, `^` and `$` as anchors and `scope:<name>` as filter
+  #[clap(add = ArgValueCompleter::new(complete_name))]
   pub(super) query: String,
 
   #[clap(short, long, default_value = "index.ixx")]
   pub(super) index: PathBuf,
 
   #[clap(short, long, default_value = "0")]
-  pub(super) scope_id: Option<u8>,
+  pub(super) scope_id: Option<u32>,
 
   #[clap(short, long, default_value = "10")]
   pub(super) max_results: u32,
 
+  /// skip this many of the best results, to show further pages
+  #[clap(long, default_value = "0")]
+  pub(super) offset: u32,
+
+  /// also find names which only match with a few typos
+  #[clap(long)]
+  pub(super) fuzzy: bool,
+
+  /// only find names matching the query with the same case
+  #[clap(long)]
+  pub(super) case_sensitive: bool,
+
+  /// treat the query as a regular expression matched against the full names
+  #[clap(long, conflicts_with_all = ["fuzzy", "case_sensitive"])]
+  pub(super) regex: bool,
+
+  #[clap(short, long, default_value = "text")]
+  pub(super) format: Format,
+}
+
+#[derive(Parser)]
+pub(super) struct ShowModule {
+  /// e.g. `services.nginx.virtualHosts.example.root`
+  #[clap(add = ArgValueCompleter::new(complete_name))]
+  pub(super) path: String,
+
+  #[clap(short, long, default_value = "index.ixx")]
+  pub(super) index: PathBuf,
+
+  /// directory with the chunks written next to the index, to print the whole entry
+  #[clap(short, long)]
+  pub(super) chunks: Option<PathBuf>,
+
+  #[clap(short, long, default_value = "0")]
+  pub(super) scope_id: u32,
+
   #[clap(short, long, default_value = "text")]
   pub(super) format: Format,
 }
@@ -69,3 +119,38 @@ pub(super) struct MetaModule {
   #[clap(short, long, default_value = "text")]
   pub(super) format: Format,
 }
+
+#[derive(Parser)]
+pub(super) struct LsModule {
+  /// e.g. `services.nginx`, lists the first labels of all names if omitted
+  #[clap(default_value = "", add = ArgValueCompleter::new(complete_name))]
+  pub(super) prefix: String,
+
+  #[clap(short, long, default_value = "index.ixx")]
+  pub(super) index: PathBuf,
+
+  #[clap(short, long, default_value = "0")]
+  pub(super) scope_id: u32,
+
+  #[clap(short, long, default_value = "text")]
+  pub(super) format: Format,
+}
+
+#[derive(Parser)]
+pub(super) struct DiffModule {
+  pub(super) old: PathBuf,
+
+  pub(super) new: PathBuf,
+
+  /// chunks written next to the old index, to also show entries whose type, default or description
+  /// changed
+  #[clap(long, requires = "new_chunks")]
+  pub(super) old_chunks: Option<PathBuf>,
+
+  /// chunks written next to the new index
+  #[clap(long, requires = "old_chunks")]
+  pub(super) new_chunks: Option<PathBuf>,
+
+  #[clap(short, long, default_value = "text")]
+  pub(super) format: Format,
+}
@@ -0,0 +1,66 @@
+use std::{ffi::OsStr, fs};
+
+use clap_complete::CompletionCandidate;
+use libixx::Index;
+
+/// Completes names from the index given on the command line, like `services.ngi` to
+/// `services.nginx`.
+///
+/// Values are completed before the arguments are parsed, so the index and scope are taken from the
+/// raw arguments, falling back to the same defaults as the subcommands.
+pub(crate) fn complete_name(current: &OsStr) -> Vec<CompletionCandidate> {
+  let Some(current) = current.to_str() else {
+    return Vec::new();
+  };
+  let args = std::env::args().collect::<Vec<_>>();
+
+  let index = option_value(&args, "-i", "--index").unwrap_or("index.ixx");
+  let scope_id = option_value(&args, "-s", "--scope-id")
+    .and_then(|scope_id| scope_id.parse().ok())
+    .unwrap_or(0);
+
+  let Some(index) = fs::read(index).ok().and_then(|buf| Index::read(&buf).ok()) else {
+    return Vec::new();
+  };
+
+  index
+    .complete(scope_id, current, 50)
+    .into_iter()
+    .map(CompletionCandidate::new)
+    .collect()
+}
+
+/// Last value of an option, given as `-i value`, `-ivalue`, `--index value` or `--index=value`.
+fn option_value<'a>(args: &'a [String], short: &str, long: &str) -> Option<&'a str> {
+  let mut value = None;
+
+  for (idx, arg) in args.iter().enumerate() {
+    if arg == short || arg == long {
+      value = args.get(idx + 1).map(String::as_str);
+    } else if let Some(rest) = arg.strip_prefix(long).and_then(|rest| rest.strip_prefix('=')) {
+      value = Some(rest);
+    } else if let Some(rest) = arg.strip_prefix(short)
+      && !rest.is_empty()
+    {
+      value = Some(rest);
+    }
+  }
+
+  value
+}
+
+#[cfg(test)]
+mod test {
+  use super::option_value;
+
+  #[test]
+  fn test_option_value() {
+    let args = ["ixx", "search", "-i", "a.ixx", "--scope-id=2", "nginx"].map(String::from);
+    assert_eq!(option_value(&args, "-i", "--index"), Some("a.ixx"));
+    assert_eq!(option_value(&args, "-s", "--scope-id"), Some("2"));
+
+    let args = ["ixx", "show", "-ib.ixx", "--index", "c.ixx", "-s"].map(String::from);
+    assert_eq!(option_value(&args, "-i", "--index"), Some("c.ixx"));
+    assert_eq!(option_value(&args, "-s", "--scope-id"), None);
+  }
+}
@@ -1,21 +1,30 @@
 use args::{Action, Args};
-use clap::Parser;
+use clap::{CommandFactory, Parser};
+use clap_complete::CompleteEnv;
 use serde::Deserialize;
 use url::Url;
 
 mod action;
 mod args;
+mod completion;
 mod option;
 mod package;
 pub(crate) mod utils;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+  // `source <(COMPLETE=bash ixx)` enables completions, including names from the index
+  CompleteEnv::with_factory(Args::command).complete();
+
   let args = Args::parse();
 
   match args.action {
     Action::Index(module) => action::index::index(module).await,
     Action::Search(module) => action::search::search(module),
+    Action::Meta(module) => action::meta::meta(module),
+    Action::Show(module) => action::show::show(module),
+    Action::Ls(module) => action::ls::ls(module),
+    Action::Diff(module) => action::diff::diff(module),
   }?;
 
   Ok(())
@@ -1,6 +1,6 @@
 use criterion::{Criterion, criterion_group, criterion_main};
-use libixx::Index;
-use std::{fs::File, hint::black_box};
+use libixx::{Index, IndexView, SearchOptions};
+use std::{fs::File, hint::black_box, io::Read};
 
 fn criterion_benchmark(c: &mut Criterion) {
   let mut file = match File::open("../index.ixx") {
@@ -13,12 +13,37 @@ fn criterion_benchmark(c: &mut Criterion) {
       std::process::exit(1);
     }
   };
-  let index = Index::read_from(&mut file).unwrap();
+  let mut compressed = Vec::new();
+  file.read_to_end(&mut compressed).unwrap();
+  let index = Index::read(&compressed).unwrap();
+
+  c.bench_function("read index", |b| b.iter(|| Index::read(black_box(&compressed))));
+
+  c.bench_function("read index view", |b| {
+    b.iter(|| IndexView::read(black_box(&compressed)))
+  });
 
   c.bench_function("search for hello", |b| {
     b.iter(|| index.search(None, black_box("hello"), 500))
   });
 
+  c.bench_function("search for hello (top 10)", |b| {
+    b.iter(|| index.search(None, black_box("hello"), 10))
+  });
+
+  // matches almost every entry, so this mostly measures maintaining the top results
+  c.bench_function("search for e", |b| {
+    b.iter(|| index.search(None, black_box("e"), 500))
+  });
+
+  let fuzzy = SearchOptions {
+    fuzzy: true,
+    ..SearchOptions::default()
+  };
+  c.bench_function("fuzzy search for pyhton313Packages.cryptograhpy", |b| {
+    b.iter(|| index.search_with(None, black_box("pyhton313Packages.cryptograhpy"), 500, &fuzzy))
+  });
+
   c.bench_function("search for zoo", |b| {
     b.iter(|| index.search(None, black_box("zoo"), 500))
   });
@@ -50,6 +75,18 @@ fn criterion_benchmark(c: &mut Criterion) {
 ",
     |b| b.iter(|| index.search(None, black_box("haskell.packages.ghc*.Facebook-*-Version"), 500)),
   );
+
+  c.bench_function("get_idx_by_name hello", |b| {
+    b.iter(|| index.get_idx_by_name(0, black_box("hello")))
+  });
+
+  c.bench_function("get_idx_by_name python313Packages.cryptography", |b| {
+    b.iter(|| index.get_idx_by_name(0, black_box("python313Packages.cryptography")))
+  });
+
+  c.bench_function("get_idx_by_name nonexistent", |b| {
+    b.iter(|| index.get_idx_by_name(0, black_box("python313Packages.nonexistent")))
+  });
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -0,0 +1,222 @@
+use std::{borrow::Cow, fmt};
+
+/// Splits a Nix attribute path like `services.nginx.virtualHosts."example.com".root` into its labels,
+/// unquoting the quoted ones.
+pub(crate) fn parse(path: &str) -> Result<Vec<Cow<'_, str>>, &'static str> {
+  let mut labels = Vec::new();
+  let mut rest = path;
+
+  loop {
+    let (label, quoted, remainder) = next_label(rest, b".", false)?;
+    if label.is_empty() && !quoted {
+      return Err("empty label");
+    }
+    labels.push(label);
+
+    match remainder.strip_prefix('.') {
+      Some(remainder) => rest = remainder,
+      None => return Ok(labels),
+    }
+  }
+}
+
+/// Reads the label at the start of `path`, returning it, whether it was quoted and everything after it,
+/// starting with the separator.
+///
+/// When `lenient`, this never fails: an unterminated quote extends to the end of `path` and anything up
+/// to the next separator after a closing quote is part of the label.
+pub(crate) fn next_label<'a>(
+  path: &'a str,
+  separators: &[u8],
+  lenient: bool,
+) -> Result<(Cow<'a, str>, bool, &'a str), &'static str> {
+  let plain_end = |path: &str| {
+    path
+      .bytes()
+      .position(|byte| separators.contains(&byte))
+      .unwrap_or(path.len())
+  };
+
+  if let Some(quoted) = path.strip_prefix('"') {
+    let mut label = String::new();
+    let mut chars = quoted.char_indices();
+
+    loop {
+      match chars.next() {
+        Some((idx, '"')) => {
+          let rest = &quoted[idx + 1..];
+          let end = plain_end(rest);
+          if end != 0 {
+            if !lenient {
+              return Err("unexpected character after closing quote");
+            }
+            label.push_str(&rest[..end]);
+          }
+          return Ok((label.into(), true, &rest[end..]));
+        }
+        Some((_, '\\')) => match chars.next() {
+          Some((_, 'n')) => label.push('\n'),
+          Some((_, 'r')) => label.push('\r'),
+          Some((_, 't')) => label.push('\t'),
+          Some((_, char)) => label.push(char),
+          None if lenient => return Ok((label.into(), true, "")),
+          None => return Err("unterminated quote"),
+        },
+        Some((_, char)) => label.push(char),
+        None if lenient => return Ok((label.into(), true, "")),
+        None => return Err("unterminated quote"),
+      }
+    }
+  }
+
+  // placeholders like `<name>` or `<function body>` are not quoted, even if they contain separators
+  if path.starts_with('<') {
+    for (idx, _) in path.match_indices('>') {
+      let rest = &path[idx + 1..];
+      if rest.bytes().next().is_none_or(|byte| separators.contains(&byte)) {
+        return Ok((Cow::Borrowed(&path[..=idx]), false, rest));
+      }
+    }
+  }
+
+  let end = plain_end(path);
+  Ok((Cow::Borrowed(&path[..end]), false, &path[end..]))
+}
+
+/// Whether `label` has to be quoted inside of an attribute path, following `lib.showOption`.
+pub(crate) fn needs_quoting(label: &[u8]) -> bool {
+  // would be split apart again by parse
+  if is_placeholder(label) && !label.windows(2).any(|window| window == b">.") {
+    return false;
+  }
+
+  match label.split_first() {
+    Some((first, rest)) => {
+      !(first.is_ascii_alphabetic() || *first == b'_')
+        || !rest
+          .iter()
+          .all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'\'' | b'-'))
+    }
+    None => true,
+  }
+}
+
+/// Whether `label` stands in for any attribute name, like `*` or `<name>` in option names.
+pub(crate) fn is_placeholder(label: &[u8]) -> bool {
+  label == b"*" || (label.len() > 2 && label.starts_with(b"<") && label.ends_with(b">"))
+}
+
+/// Writes `label` as it appears in an attribute path, quoted and escaped if necessary.
+pub(crate) fn write_label(f: &mut impl fmt::Write, label: &str) -> fmt::Result {
+  if !needs_quoting(label.as_bytes()) {
+    return f.write_str(label);
+  }
+
+  f.write_char('"')?;
+  for char in label.chars() {
+    match char {
+      '"' => f.write_str("\\\"")?,
+      '\\' => f.write_str("\\\\")?,
+      '$' => f.write_str("\\$")?,
+      '\n' => f.write_str("\\n")?,
+      '\r' => f.write_str("\\r")?,
+      '\t' => f.write_str("\\t")?,
+      char => f.write_char(char)?,
+    }
+  }
+  f.write_char('"')
+}
+
+/// Position of byte `idx` of `label` in the output of [`write_label`].
+pub(crate) fn rendered_offset(label: &[u8], idx: usize) -> usize {
+  if !needs_quoting(label) {
+    return idx;
+  }
+
+  1 + label[..idx]
+    .iter()
+    .map(|byte| match byte {
+      b'"' | b'\\' | b'$' | b'\n' | b'\r' | b'\t' => 2,
+      _ => 1,
+    })
+    .sum::<usize>()
+}
+
+/// Length of the output of [`write_label`].
+pub(crate) fn rendered_len(label: &[u8]) -> usize {
+  if needs_quoting(label) {
+    rendered_offset(label, label.len()) + 1
+  } else {
+    label.len()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn render(label: &str) -> String {
+    let mut rendered = String::new();
+    write_label(&mut rendered, label).unwrap();
+    rendered
+  }
+
+  #[test]
+  fn parse_paths() {
+    assert_eq!(parse("foo.bar").unwrap(), vec!["foo", "bar"]);
+    assert_eq!(
+      parse(r#"services.nginx.virtualHosts."example.com".root"#).unwrap(),
+      vec!["services", "nginx", "virtualHosts", "example.com", "root"]
+    );
+    assert_eq!(
+      parse(r#"foo."a \"b\" \\ \$c\n".bar"#).unwrap(),
+      vec!["foo", "a \"b\" \\ $c\n", "bar"]
+    );
+    assert_eq!(parse(r#"foo."""#).unwrap(), vec!["foo", ""]);
+    assert_eq!(
+      parse("services.<name>.<function body>.*").unwrap(),
+      vec!["services", "<name>", "<function body>", "*"]
+    );
+    assert_eq!(
+      parse("<imports = [ pkgs.foo ]>.enable").unwrap(),
+      vec!["<imports = [ pkgs.foo ]>", "enable"]
+    );
+    assert_eq!(parse("<a.b").unwrap(), vec!["<a", "b"]);
+    assert_eq!(parse(r#"foo"bar.baz"#).unwrap(), vec![r#"foo"bar"#, "baz"]);
+  }
+
+  #[test]
+  fn parse_errors() {
+    assert_eq!(parse(""), Err("empty label"));
+    assert_eq!(parse("foo..bar"), Err("empty label"));
+    assert_eq!(parse("foo."), Err("empty label"));
+    assert_eq!(parse(r#"foo."bar"#), Err("unterminated quote"));
+    assert_eq!(parse(r#"foo."bar\"#), Err("unterminated quote"));
+    assert_eq!(
+      parse(r#"foo."bar"baz"#),
+      Err("unexpected character after closing quote")
+    );
+  }
+
+  #[test]
+  fn render_labels() {
+    for (label, rendered) in [
+      ("foo", "foo"),
+      ("foo-bar_baz'", "foo-bar_baz'"),
+      ("<name>", "<name>"),
+      ("*", "*"),
+      ("example.com", r#""example.com""#),
+      ("1password", r#""1password""#),
+      ("", r#""""#),
+      ("a \"b\" \\ ${c}\n", r#""a \"b\" \\ \${c}\n""#),
+      ("<a>.b>", r#""<a>.b>""#),
+    ] {
+      assert_eq!(render(label), rendered);
+      assert_eq!(rendered_len(label.as_bytes()), rendered.len());
+      assert_eq!(parse(rendered).unwrap(), vec![label]);
+    }
+
+    assert_eq!(rendered_offset(b"foo", 2), 2);
+    assert_eq!(rendered_offset(b"a\"b", 2), 4);
+  }
+}
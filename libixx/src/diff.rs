@@ -0,0 +1,104 @@
+use std::collections::BTreeMap;
+
+use crate::{lookup, storage::Storage};
+
+/// Differences between the entries of two indexes, see [`crate::Index::diff`].
+///
+/// Entries are matched by name and scope, scopes are matched by name if both indexes have scope names
+/// and by id otherwise, like for `ixx02` indexes. Scope ids are the ones in the index containing the
+/// entry. Every list is ordered by name.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct IndexDiff {
+  /// entries only in the new index
+  pub added: Vec<DiffEntry>,
+  /// entries only in the old index
+  pub removed: Vec<DiffEntry>,
+  /// names which are in another scope in the new index
+  pub moved: Vec<MovedEntry>,
+  /// indexes of the entries in both indexes, in the old and the new one
+  pub unchanged: Vec<(usize, usize)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffEntry {
+  pub name: String,
+  pub scope_id: u32,
+  /// index in the index containing the entry
+  pub idx: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MovedEntry {
+  pub name: String,
+  pub old_scope_id: u32,
+  pub new_scope_id: u32,
+  pub old_idx: usize,
+  pub new_idx: usize,
+}
+
+pub(crate) fn diff(old: &impl Storage, new: &impl Storage) -> IndexDiff {
+  let old_scopes = &old.metadata().scopes;
+  let new_scopes = &new.metadata().scopes;
+  // scope id in the old index for every scope id of the new one, `None` for new scopes
+  let old_scope_id = |new_scope_id: u32| {
+    if old_scopes.is_empty() || new_scopes.is_empty() {
+      return Some(new_scope_id);
+    }
+    let name = new_scopes.get(new_scope_id as usize)?;
+    old_scopes
+      .iter()
+      .position(|old_name| old_name == name)
+      .map(|scope_id| scope_id as u32)
+  };
+
+  // scope ids and indexes of the entries with every name, for the old and the new index
+  let mut names = BTreeMap::<String, (Vec<(u32, usize)>, Vec<(u32, usize)>)>::new();
+  for (idx, scope_id, name) in lookup::entries(old) {
+    names.entry(name).or_default().0.push((scope_id, idx));
+  }
+  for (idx, scope_id, name) in lookup::entries(new) {
+    names.entry(name).or_default().1.push((scope_id, idx));
+  }
+
+  let mut diff = IndexDiff::default();
+
+  for (name, (old_entries, mut new_entries)) in names {
+    let mut removed = Vec::new();
+    for (scope_id, old_idx) in old_entries {
+      match new_entries
+        .iter()
+        .position(|(new_scope_id, _)| old_scope_id(*new_scope_id) == Some(scope_id))
+      {
+        Some(position) => {
+          let (_, new_idx) = new_entries.remove(position);
+          diff.unchanged.push((old_idx, new_idx));
+        }
+        None => removed.push((scope_id, old_idx)),
+      }
+    }
+
+    // the same name left one scope and appeared in another one
+    let moved = removed.len().min(new_entries.len());
+    for ((old_scope_id, old_idx), (new_scope_id, new_idx)) in
+      removed.drain(..moved).zip(new_entries.drain(..moved))
+    {
+      diff.moved.push(MovedEntry {
+        name: name.clone(),
+        old_scope_id,
+        new_scope_id,
+        old_idx,
+        new_idx,
+      });
+    }
+
+    for (entries, list) in [(removed, &mut diff.removed), (new_entries, &mut diff.added)] {
+      list.extend(entries.into_iter().map(|(scope_id, idx)| DiffEntry {
+        name: name.clone(),
+        scope_id,
+        idx,
+      }));
+    }
+  }
+
+  diff
+}
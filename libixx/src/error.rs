@@ -9,6 +9,15 @@ pub enum IxxError {
   InvalidLabelReference,
   #[error("recursive reference")]
   RecursiveReference,
+  #[error("malformed index: {0}")]
+  MalformedIndex(&'static str),
+  #[error("invalid name {name:?}: {reason}")]
+  InvalidName { name: String, reason: &'static str },
+  #[error("invalid query {query:?}: {reason}")]
+  InvalidQuery { query: String, reason: &'static str },
+  #[cfg(feature = "regex")]
+  #[error("invalid regular expression: {0}")]
+  InvalidRegex(#[from] regex::Error),
 
   #[error("(de)serialization failed")]
   Binrw(#[from] binrw::Error),
@@ -1,5 +1,5 @@
 use std::{
-  collections::HashMap,
+  collections::{HashMap, HashSet},
   io::{Cursor, Read, Seek, Write},
   string::FromUtf8Error,
 };
@@ -7,50 +7,141 @@ use std::{
 use binrw::{BinRead, BinWrite, Endian, binrw};
 use lzma_rust2::{XzOptions, XzReader, XzWriter};
 
-use levenshtein::levenshtein;
+use crate::{
+  IndexDiff, IxxError, Metadata, SearchHit, SearchOptions, SearchPage, attr_path, diff,
+  lookup::{self, Child, Lookup},
+  metadata::FORMAT_VERSION,
+  search::{self, SearchIter},
+  storage::Storage,
+  suggest,
+  trigram::{LazyTrigrams, Trigrams},
+};
 
-use crate::{IxxError, string_view::StringView};
+const MAGIC_V2: [u8; 5] = *b"ixx02";
+const MAGIC_V3: [u8; 5] = *b"ixx03";
 
 #[binrw]
-#[brw(magic = b"ixx02")]
 #[derive(Debug, Clone, PartialEq)]
 pub struct Index {
+  #[br(assert(
+    magic == MAGIC_V2 || magic == MAGIC_V3,
+    "unsupported index format {:?}",
+    String::from_utf8_lossy(&magic)
+  ))]
+  #[bw(calc = MAGIC_V3)]
+  magic: [u8; 5],
+  #[br(if(magic == MAGIC_V3, Metadata::legacy()), args(FORMAT_VERSION))]
+  pub(crate) metadata: Metadata,
   #[bw(calc = labels.len() as u32)]
   label_count: u32,
-  #[br(count = label_count)]
+  #[br(count = label_count, args { inner: (metadata.format_version,) })]
   pub(crate) labels: Vec<PascalString>,
   #[bw(calc = entries.len() as u32)]
   entry_count: u32,
-  #[br(count = entry_count)]
+  #[br(count = entry_count, args { inner: (metadata.format_version,) })]
   pub(crate) entries: Vec<Entry>,
+  #[br(calc = Lookup::new(
+    labels.iter().map(|label| label.data.as_slice()),
+    entries.iter().map(|entry| (entry.scope_id, entry.labels.as_slice())),
+  ))]
+  #[bw(ignore)]
+  pub(crate) lookup: Lookup,
+  #[br(calc = LazyTrigrams::default())]
+  #[bw(ignore)]
+  pub(crate) trigrams: LazyTrigrams,
 }
 
 #[binrw]
+#[br(import(format_version: u8))]
 #[derive(Debug, Clone, PartialEq)]
 pub struct PascalString {
-  #[bw(calc = data.len() as u8)]
-  len: u8,
-  #[br(count = len)]
+  #[br(parse_with = read_length, args(format_version))]
+  #[bw(calc = VarInt(data.len() as u64))]
+  len: VarInt,
+  #[br(count = len.0)]
   pub(crate) data: Vec<u8>,
 }
 
 #[binrw]
+#[br(import(format_version: u8))]
 #[derive(Default, Debug, Clone, PartialEq)]
 pub struct Entry {
   /// index in the scopes Vec
-  pub(crate) scope_id: u8,
-  #[bw(calc = labels.len() as u8)]
-  label_count: u8,
-  #[br(count = label_count)]
+  #[br(parse_with = read_scope_id, args(format_version))]
+  #[bw(write_with = write_scope_id)]
+  pub(crate) scope_id: u32,
+  #[br(parse_with = read_length, args(format_version))]
+  #[bw(calc = VarInt(labels.len() as u64))]
+  label_count: VarInt,
+  #[br(count = label_count.0)]
   pub(crate) labels: Vec<LabelReference>,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+/// Lengths are a single byte in `ixx02` and a [`VarInt`] since `ixx03`.
+#[binrw::parser(reader, endian)]
+fn read_length(format_version: u8) -> binrw::BinResult<VarInt> {
+  if format_version < 3 {
+    Ok(VarInt(u64::from(u8::read_options(reader, endian, ())?)))
+  } else {
+    VarInt::read_options(reader, endian, ())
+  }
+}
+
+/// Scope ids are a single byte in `ixx02` and a [`VarInt`] since `ixx03`.
+#[binrw::parser(reader, endian)]
+fn read_scope_id(format_version: u8) -> binrw::BinResult<u32> {
+  if format_version < 3 {
+    return Ok(u32::from(u8::read_options(reader, endian, ())?));
+  }
+
+  let pos = reader.stream_position()?;
+  u32::try_from(VarInt::read_options(reader, endian, ())?.0).map_err(|_| binrw::Error::AssertFail {
+    pos,
+    message: "scope id out of range".into(),
+  })
+}
+
+#[binrw::writer(writer, endian)]
+fn write_scope_id(scope_id: &u32) -> binrw::BinResult<()> {
+  VarInt(u64::from(*scope_id)).write_options(writer, endian, ())
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct LabelReference(pub u64);
 
 impl BinRead for LabelReference {
   type Args<'a> = ();
 
+  fn read_options<R: Read + Seek>(
+    reader: &mut R,
+    endian: Endian,
+    args: Self::Args<'_>,
+  ) -> binrw::BinResult<Self> {
+    Ok(Self(VarInt::read_options(reader, endian, args)?.0))
+  }
+}
+
+impl BinWrite for LabelReference {
+  type Args<'a> = ();
+
+  fn write_options<W: Write + Seek>(
+    &self,
+    writer: &mut W,
+    endian: Endian,
+    args: Self::Args<'_>,
+  ) -> binrw::BinResult<()> {
+    VarInt(self.0).write_options(writer, endian, args)
+  }
+}
+
+/// Unsigned integer prefixed with a size tag, `0` for a following `u8`, `1` for `u16`, `2` for `u32`
+/// and `3` for `u64`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct VarInt(pub(crate) u64);
+
+impl BinRead for VarInt {
+  type Args<'a> = ();
+
   fn read_options<R: Read + Seek>(
     reader: &mut R,
     endian: Endian,
@@ -65,13 +156,13 @@ impl BinRead for LabelReference {
       3 => Ok(Self(u64::read_options(reader, endian, ())?)),
       _ => Err(binrw::Error::AssertFail {
         pos: reader.stream_position()?,
-        message: "Invalid label integer size".into(),
+        message: "Invalid integer size".into(),
       }),
     }
   }
 }
 
-impl BinWrite for LabelReference {
+impl BinWrite for VarInt {
   type Args<'a> = ();
 
   fn write_options<W: Write + Seek>(
@@ -100,25 +191,17 @@ impl BinWrite for LabelReference {
 
 impl From<String> for PascalString {
   fn from(value: String) -> Self {
-    let bytes = value.into_bytes();
-    assert!(
-      bytes.len() <= 255,
-      "PascalString data exceeds maximum length of 255 bytes: {}",
-      bytes.len()
-    );
-    Self { data: bytes }
+    Self {
+      data: value.into_bytes(),
+    }
   }
 }
 
 impl From<&str> for PascalString {
   fn from(value: &str) -> Self {
-    let bytes = value.as_bytes();
-    assert!(
-      bytes.len() <= 255,
-      "PascalString data exceeds maximum length of 255 bytes: {}",
-      bytes.len()
-    );
-    Self { data: bytes.to_vec() }
+    Self {
+      data: value.as_bytes().to_vec(),
+    }
   }
 }
 
@@ -137,13 +220,57 @@ impl PartialEq<str> for &PascalString {
 }
 
 impl Index {
-  pub fn build(entries: &[(&str, u8)]) -> Self {
+  pub(crate) fn new(labels: Vec<PascalString>, entries: Vec<Entry>) -> Self {
+    let lookup = Lookup::new(
+      labels.iter().map(|label| label.data.as_slice()),
+      entries
+        .iter()
+        .map(|entry| (entry.scope_id, entry.labels.as_slice())),
+    );
+
+    Self {
+      metadata: Metadata::default(),
+      labels,
+      entries,
+      lookup,
+      trigrams: LazyTrigrams::default(),
+    }
+  }
+
+  /// Replaces the header, which is written along with the index.
+  #[must_use]
+  pub fn with_metadata(mut self, metadata: Metadata) -> Self {
+    self.metadata = metadata;
+    self
+  }
+
+  #[must_use]
+  pub fn metadata(&self) -> &Metadata {
+    &self.metadata
+  }
+
+  /// Builds an index from attribute paths like `services.nginx.virtualHosts."example.com".root` and
+  /// the ids of their scopes.
+  ///
+  /// Names which are no valid attribute paths, like `foo..bar` or `foo."bar`, are rejected.
+  pub fn build(entries: &[(&str, u32)]) -> Result<Self, IxxError> {
+    let entries = entries
+      .iter()
+      .map(|(name, scope_id)| match attr_path::parse(name) {
+        Ok(labels) => Ok((labels, *scope_id)),
+        Err(reason) => Err(IxxError::InvalidName {
+          name: name.to_string(),
+          reason,
+        }),
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
     let mut labels = HashMap::new();
 
-    for (entry, _) in entries {
-      for label in entry.split('.') {
+    for (entry, _) in &entries {
+      for label in entry {
         labels
-          .entry(label)
+          .entry(label.as_ref())
           .and_modify(|count| *count += 1u64)
           .or_insert(1u64);
       }
@@ -151,7 +278,8 @@ impl Index {
 
     let mut labels = labels.into_iter().collect::<Vec<_>>();
 
-    labels.sort_by(|(_, a), (_, b)| a.cmp(b).reverse());
+    // ties are broken by content, so the same entries always give the same label table
+    labels.sort_by(|(a_label, a), (b_label, b)| b.cmp(a).then_with(|| a_label.cmp(b_label)));
 
     let labels_lookup = labels
       .iter()
@@ -159,18 +287,21 @@ impl Index {
       .map(|(idx, (label, _))| (label, idx))
       .collect::<HashMap<_, _>>();
 
-    let labels = labels.iter().map(|(label, _)| label.to_string().into()).collect();
+    let labels = labels
+      .iter()
+      .map(|(label, _)| label.to_string().into())
+      .collect::<Vec<_>>();
 
     let entries = entries
       .iter()
       .map(|(entry, scope_id)| Entry {
         scope_id: *scope_id,
         labels: entry
-          .split('.')
+          .iter()
           .map(|label| {
             LabelReference(
               *labels_lookup
-                .get(&label)
+                .get(&label.as_ref())
                 .expect("this can not happen, the hashmap was build based on the same data")
                 as u64,
             )
@@ -179,14 +310,130 @@ impl Index {
       })
       .collect();
 
-    Index { labels, entries }
+    Ok(Index::new(labels, entries))
+  }
+
+  /// Builds an index with the entries of both indexes, keeping the metadata of `self`.
+  ///
+  /// Entries are ordered by name and then scope id like `ixx index` orders them for chunking, and
+  /// entries in both indexes are only kept once. Scopes of `other` are matched to the ones of `self`
+  /// by name and missing ones are added, if either index has no scope names the scope ids are taken
+  /// as they are.
+  pub fn merge(&self, other: &Index) -> Result<Self, IxxError> {
+    let (metadata, scope_ids) = self.merged_scopes(other);
+
+    let mut entries = self
+      .entries()
+      .map(|(_, scope_id, name)| (name, scope_id))
+      .chain(
+        other
+          .entries()
+          .map(|(_, scope_id, name)| (name, scope_ids(scope_id))),
+      )
+      .collect::<Vec<_>>();
+    entries.sort();
+    entries.dedup();
+
+    Self::rebuild(&entries, metadata)
+  }
+
+  /// Builds an index where the entries of `scope_id` are replaced with the ones of the same scope in
+  /// `other`, like after indexing a single scope again.
+  ///
+  /// Scopes are matched like in [`Self::merge`], entries of `other` in other scopes are ignored.
+  pub fn with_scope_replaced(&self, scope_id: u32, other: &Index) -> Result<Self, IxxError> {
+    let (metadata, scope_ids) = self.merged_scopes(other);
+
+    let mut entries = self
+      .entries()
+      .map(|(_, scope_id, name)| (name, scope_id))
+      .filter(|(_, entry_scope_id)| *entry_scope_id != scope_id)
+      .chain(
+        other
+          .entries()
+          .map(|(_, scope_id, name)| (name, scope_ids(scope_id)))
+          .filter(|(_, entry_scope_id)| *entry_scope_id == scope_id),
+      )
+      .collect::<Vec<_>>();
+    entries.sort();
+    entries.dedup();
+
+    Self::rebuild(&entries, metadata)
+  }
+
+  /// Metadata of `self` with the scopes of `other` which are missing in it, and the scope id in it
+  /// for each scope id of `other`.
+  fn merged_scopes(&self, other: &Index) -> (Metadata, impl Fn(u32) -> u32 + use<>) {
+    let mut metadata = self.metadata.clone();
+    let mut scope_ids = Vec::new();
+
+    if !metadata.scopes.is_empty() {
+      for scope in &other.metadata.scopes {
+        let scope_id = match metadata.scopes.iter().position(|known| known == scope) {
+          Some(scope_id) => scope_id,
+          None => {
+            metadata.scopes.push(scope.clone());
+            metadata.scopes.len() - 1
+          }
+        };
+        scope_ids.push(scope_id as u32);
+      }
+    }
+
+    let scope_ids = move |scope_id: u32| scope_ids.get(scope_id as usize).copied().unwrap_or(scope_id);
+    (metadata, scope_ids)
+  }
+
+  fn rebuild(entries: &[(String, u32)], metadata: Metadata) -> Result<Self, IxxError> {
+    Ok(
+      Self::build(
+        entries
+          .iter()
+          .map(|(name, scope_id)| (name.as_str(), *scope_id))
+          .collect::<Vec<_>>()
+          .as_slice(),
+      )?
+      .with_metadata(metadata),
+    )
+  }
+
+  /// Compares the entries of `self` to the ones of a newer build of the index.
+  #[must_use]
+  pub fn diff(&self, new: &Index) -> IndexDiff {
+    diff::diff(self, new)
+  }
+
+  /// Same as [`Self::merge`] with an index built from `entries`.
+  pub fn with_added(&self, entries: &[(&str, u32)]) -> Result<Self, IxxError> {
+    self.merge(&Self::build(entries)?)
+  }
+
+  /// Builds an index without the entries named like any of `entries` in the same scope, keeping the
+  /// order of the others.
+  ///
+  /// Names which are not in the index are skipped, compare the [`Self::size`] of both indexes to
+  /// find out whether all of them were removed.
+  pub fn with_removed(&self, entries: &[(&str, u32)]) -> Result<Self, IxxError> {
+    // names as the index renders them, as the same name may be quoted in different ways
+    let removed = entries
+      .iter()
+      .filter_map(|(name, scope_id)| {
+        let idx = self.get_idx_by_name(*scope_id, name)?;
+        Some((self.name_of(idx)?, *scope_id))
+      })
+      .collect::<HashSet<_>>();
+
+    let kept = self
+      .entries()
+      .map(|(_, scope_id, name)| (name, scope_id))
+      .filter(|entry| !removed.contains(entry))
+      .collect::<Vec<_>>();
+
+    Self::rebuild(&kept, self.metadata.clone())
   }
 
   pub fn read(buf: &[u8]) -> Result<Self, IxxError> {
-    let cursor = Cursor::new(buf);
-    let mut decoder = XzReader::new(cursor, false);
-    let mut decompressed = Vec::new();
-    decoder.read_to_end(&mut decompressed)?;
+    let decompressed = decompress(buf)?;
     Ok(BinRead::read_options(
       &mut Cursor::new(decompressed),
       Endian::Little,
@@ -220,83 +467,118 @@ impl Index {
       .ok_or(IxxError::InvalidLabelReference)
   }
 
-  pub fn get_idx_by_name(&self, scope_id: u8, name: &str) -> Option<usize> {
-    let labels = name
-      .split('.')
-      .map(|segment| {
-        self
-          .labels
-          .iter()
-          .enumerate()
-          .find(|(_, label)| label == segment)
-          .map(|(idx, _)| LabelReference(idx as u64))
-      })
-      .collect::<Option<Vec<_>>>()?;
+  pub fn get_idx_by_name(&self, scope_id: u32, name: &str) -> Option<usize> {
+    lookup::get_idx_by_name(self, scope_id, name)
+  }
 
-    self
-      .entries
-      .iter()
-      .enumerate()
-      .find(|(_, entry)| entry.scope_id == scope_id && entry.labels == labels)
-      .map(|(idx, _)| idx)
+  /// Resolves a path from a configuration to the entry declaring it, treating placeholder labels like
+  /// `<name>` or `*` as wildcards.
+  pub fn get_idx_by_config_path(&self, scope_id: u32, path: &str) -> Option<usize> {
+    lookup::get_idx_by_config_path(self, scope_id, path)
+  }
+
+  /// Returns the name of the entry at `idx`, as returned by searches.
+  pub fn name_of(&self, idx: usize) -> Option<String> {
+    lookup::name_of(self, idx)
+  }
+
+  pub fn scope_of(&self, idx: usize) -> Option<u32> {
+    Some(self.entry(idx)?.0)
+  }
+
+  /// Iterates over the index, scope id and name of every entry.
+  pub fn entries(&self) -> impl Iterator<Item = (usize, u32, String)> {
+    lookup::entries(self)
+  }
+
+  /// Lists the distinct labels directly below the attribute path `prefix`, or the first labels of all
+  /// names if it is empty, ordered by label.
+  pub fn children(&self, scope_id: u32, prefix: &str) -> Vec<Child> {
+    lookup::children(self, scope_id, prefix)
+  }
+
+  /// Completes the last label of a partially typed attribute path like `services.ngi` to up to
+  /// `max_results` existing paths, like `services.nginx`, most frequent labels first.
+  pub fn complete(&self, scope_id: u32, partial: &str, max_results: usize) -> Vec<String> {
+    lookup::complete(self, scope_id, partial, max_results)
   }
 
   pub fn search(
     &self,
-    scope_id: Option<u8>,
+    scope_id: Option<u32>,
     query: &str,
     max_results: usize,
-  ) -> Result<Vec<(usize, u8, String)>, IxxError> {
-    let search = query
-      .split('*')
-      .map(str::as_bytes)
-      // * at the start or end of a string
-      .filter(|x| !x.is_empty())
-      .map(|segment| {
-        segment
-          .split(|char| *char == b'.')
-          .filter(|x| !x.is_empty())
-          .collect()
-      })
-      .collect::<Vec<Vec<_>>>();
-
-    let mut results = Vec::new();
-
-    for (
-      idx,
-      Entry {
-        scope_id: entry_scope_id,
-        labels,
-      },
-    ) in self.entries.iter().enumerate()
-    {
-      if let Some(scope_id) = scope_id
-        && *entry_scope_id != scope_id
-      {
-        continue;
-      }
+  ) -> Result<Vec<SearchHit>, IxxError> {
+    self.search_with(scope_id, query, max_results, &SearchOptions::default())
+  }
 
-      let entry_name = StringView::from((self, labels.as_slice()));
+  pub fn search_with(
+    &self,
+    scope_id: Option<u32>,
+    query: &str,
+    max_results: usize,
+    options: &SearchOptions,
+  ) -> Result<Vec<SearchHit>, IxxError> {
+    Ok(self.search_page(scope_id, query, 0, max_results, options)?.hits)
+  }
 
-      if entry_name.matches(&search)? {
-        let entry_name = entry_name.to_string();
-        let levenshtein = levenshtein(query, &entry_name);
+  /// Skips the best `offset` hits and also counts all names matching the query, to page through the
+  /// results.
+  pub fn search_page(
+    &self,
+    scope_id: Option<u32>,
+    query: &str,
+    offset: usize,
+    max_results: usize,
+    options: &SearchOptions,
+  ) -> Result<SearchPage, IxxError> {
+    search::search(self, scope_id, query, offset, max_results, options)
+  }
 
-        results.push((idx, *entry_scope_id, entry_name, levenshtein));
-        if results.len() == max_results {
-          break;
-        }
-      }
-    }
+  /// Lazily yields the hits for `query` in index order instead of ranking them, to filter them further
+  /// or stop early without checking every entry.
+  pub fn search_iter<'a>(
+    &'a self,
+    scope_id: Option<u32>,
+    query: &'a str,
+    options: &SearchOptions,
+  ) -> Result<impl Iterator<Item = Result<SearchHit, IxxError>> + use<'a>, IxxError> {
+    SearchIter::new(self, scope_id, query, options)
+  }
 
-    results.sort_by_key(|(_, _, _, levenshtein)| *levenshtein);
+  /// Finds names matching the regular expression `pattern`, see [`regex::Regex`] for the syntax.
+  #[cfg(feature = "regex")]
+  pub fn search_regex(
+    &self,
+    scope_id: Option<u32>,
+    pattern: &str,
+    max_results: usize,
+  ) -> Result<Vec<SearchHit>, IxxError> {
+    Ok(self.search_regex_page(scope_id, pattern, 0, max_results)?.hits)
+  }
 
-    let results = results
-      .into_iter()
-      .map(|(idx, entry_scope_id, entry_name, _)| (idx, entry_scope_id, entry_name))
-      .collect();
+  /// Like [`Self::search_regex`], but skips the best `offset` hits and counts all matching names.
+  #[cfg(feature = "regex")]
+  pub fn search_regex_page(
+    &self,
+    scope_id: Option<u32>,
+    pattern: &str,
+    offset: usize,
+    max_results: usize,
+  ) -> Result<SearchPage, IxxError> {
+    crate::regex_search::search_regex(self, scope_id, pattern, offset, max_results)
+  }
 
-    Ok(results)
+  /// Proposes up to `max_results` existing names close to `query`, for when searching finds nothing.
+  ///
+  /// Only names in `scope_id` are proposed, if it is given.
+  pub fn suggest(
+    &self,
+    scope_id: Option<u32>,
+    query: &str,
+    max_results: usize,
+  ) -> Result<Vec<String>, IxxError> {
+    suggest::suggest(self, scope_id, query, max_results)
   }
 
   #[must_use]
@@ -305,14 +587,55 @@ impl Index {
   }
 }
 
+pub(crate) fn decompress(buf: &[u8]) -> Result<Vec<u8>, IxxError> {
+  let mut decoder = XzReader::new(Cursor::new(buf), false);
+  let mut decompressed = Vec::new();
+  decoder.read_to_end(&mut decompressed)?;
+  Ok(decompressed)
+}
+
+impl Storage for Index {
+  fn label_count(&self) -> usize {
+    self.labels.len()
+  }
+
+  fn label(&self, idx: usize) -> Option<&[u8]> {
+    self.labels.get(idx).map(|label| label.data.as_slice())
+  }
+
+  fn entry_count(&self) -> usize {
+    self.entries.len()
+  }
+
+  fn entry(&self, idx: usize) -> Option<(u32, &[LabelReference])> {
+    self
+      .entries
+      .get(idx)
+      .map(|entry| (entry.scope_id, entry.labels.as_slice()))
+  }
+
+  fn metadata(&self) -> &Metadata {
+    &self.metadata
+  }
+
+  fn lookup(&self) -> &Lookup {
+    &self.lookup
+  }
+
+  fn trigrams(&self) -> &Trigrams {
+    self.trigrams.get_or_init(|| Trigrams::new(self.labels()))
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::{EntityKind, IndexView};
   use std::io::Cursor;
 
   #[test]
   fn build_single_entry() {
-    let index = Index::build(&[("foo.bar", 0)]);
+    let index = Index::build(&[("foo.bar", 0)]).unwrap();
 
     assert_eq!(index.entries.len(), 1);
     assert_eq!(index.labels.len(), 2);
@@ -334,7 +657,7 @@ mod tests {
 
   #[test]
   fn build_two_entries_shared_label() {
-    let index = Index::build(&[("foo.bar", 0), ("foo.baz", 0)]);
+    let index = Index::build(&[("foo.bar", 0), ("foo.baz", 0)]).unwrap();
 
     assert_eq!(index.entries.len(), 2);
 
@@ -352,7 +675,7 @@ mod tests {
 
   #[test]
   fn resolve_reference() {
-    let index = Index::build(&[("foo.bar", 0)]);
+    let index = Index::build(&[("foo.bar", 0)]).unwrap();
 
     let entry = &index.entries[0];
 
@@ -364,7 +687,7 @@ mod tests {
 
   #[test]
   fn get_idx_by_name() {
-    let index = Index::build(&[("foo.bar", 0), ("foo.baz", 1)]);
+    let index = Index::build(&[("foo.bar", 0), ("foo.baz", 1)]).unwrap();
 
     let idx = index.get_idx_by_name(0, "foo.bar");
     assert_eq!(idx, Some(0));
@@ -378,7 +701,7 @@ mod tests {
 
   #[test]
   fn write_read_roundtrip() {
-    let index = Index::build(&[("foo.bar", 0), ("foo.baz", 1)]);
+    let index = Index::build(&[("foo.bar", 0), ("foo.baz", 1)]).unwrap();
 
     let mut buf = Cursor::new(Vec::new());
     index.write_into(&mut buf).unwrap();
@@ -396,19 +719,77 @@ mod tests {
     assert_eq!(index, decoded);
   }
 
+  #[test]
+  fn write_read_roundtrip_metadata() {
+    let metadata = Metadata {
+      chunk_size: 100,
+      kind: EntityKind::Packages,
+      build_timestamp: 1_700_000_000,
+      generator: "ixx 1.2.3".to_string(),
+      scopes: vec!["NixOS".to_string(), String::new()],
+      ..Metadata::default()
+    };
+    let index = Index::build(&[("foo.bar", 0), ("foo.baz", 1)])
+      .unwrap()
+      .with_metadata(metadata.clone());
+
+    let mut buf = Cursor::new(Vec::new());
+    index.write_into(&mut buf).unwrap();
+    buf.set_position(0);
+
+    let decoded = Index::read_from(&mut buf).unwrap();
+    assert_eq!(decoded.metadata(), &metadata);
+    assert_eq!(decoded.metadata().format_version, 3);
+    assert_eq!(decoded.metadata().scope_name(0), Some("NixOS"));
+    assert_eq!(decoded.metadata().scope_name(1), None);
+    assert_eq!(index, decoded);
+  }
+
+  #[test]
+  fn read_ixx02() {
+    // foo.bar in scope 1, as written by the previous format revision
+    let mut ixx02 = b"ixx02".to_vec();
+    ixx02.extend_from_slice(&2u32.to_le_bytes());
+    ixx02.extend_from_slice(b"\x03foo\x03bar");
+    ixx02.extend_from_slice(&1u32.to_le_bytes());
+    ixx02.extend_from_slice(&[1, 2, 0, 0, 0, 1]);
+
+    let compressed = compress(&ixx02);
+
+    let index = Index::read(&compressed).unwrap();
+    assert_eq!(index.metadata(), &Metadata::legacy());
+    assert_eq!(index.metadata().format_version, 2);
+    assert_eq!(index.get_idx_by_name(1, "foo.bar"), Some(0));
+
+    let view = IndexView::read(&compressed).unwrap();
+    assert_eq!(view.metadata(), &Metadata::legacy());
+    assert_eq!(view.get_idx_by_name(1, "foo.bar"), Some(0));
+
+    let mut unknown = ixx02;
+    unknown[4] = b'1';
+    assert!(Index::read(&compress(&unknown)).is_err());
+    assert!(IndexView::new(unknown).is_err());
+  }
+
+  fn compress(data: &[u8]) -> Vec<u8> {
+    let mut encoder = XzWriter::new(Vec::new(), XzOptions::default()).unwrap();
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+  }
+
   #[test]
   fn search_exact_match() {
-    let index = Index::build(&[("foo.bar", 0), ("foo.baz", 0), ("alpha.beta", 1)]);
+    let index = Index::build(&[("foo.bar", 0), ("foo.baz", 0), ("alpha.beta", 1)]).unwrap();
 
     let results = index.search(None, "foo.bar", 10).unwrap();
 
     assert_eq!(results.len(), 1);
-    assert_eq!(results[0].2.as_str(), "foo.bar");
+    assert_eq!(results[0].name.as_str(), "foo.bar");
   }
 
   #[test]
   fn search_wildcard() {
-    let index = Index::build(&[("foo.bar", 0), ("foo.baz", 0), ("alpha.beta", 1)]);
+    let index = Index::build(&[("foo.bar", 0), ("foo.baz", 0), ("alpha.beta", 1)]).unwrap();
 
     let results = index.search(None, "foo.*", 10).unwrap();
 
@@ -417,7 +798,7 @@ mod tests {
 
   #[test]
   fn search_empty_query() {
-    let index = Index::build(&[("foo.bar", 0), ("foo.baz", 0)]);
+    let index = Index::build(&[("foo.bar", 0), ("foo.baz", 0)]).unwrap();
     let results = index.search(None, "", 10).unwrap();
     // Empty query results in empty search vector, which matches all entries
     assert_eq!(results.len(), 2);
@@ -425,7 +806,7 @@ mod tests {
 
   #[test]
   fn search_with_wildcard_only() {
-    let index = Index::build(&[("foo.bar", 0), ("foo.baz", 0)]);
+    let index = Index::build(&[("foo.bar", 0), ("foo.baz", 0)]).unwrap();
     let results = index.search(None, "*", 10).unwrap();
     // Query with only wildcards results in empty search vector, which matches all entries
     assert_eq!(results.len(), 2);
@@ -439,7 +820,8 @@ mod tests {
       ("alpha.three", 0),
       ("alpha.four", 0),
       ("alpha.five", 0),
-    ]);
+    ])
+    .unwrap();
     let results = index.search(None, "alpha", 2).unwrap();
     // Should return at most max_results items
     assert_eq!(results.len(), 2);
@@ -447,14 +829,14 @@ mod tests {
 
   #[test]
   fn search_empty_index() {
-    let index = Index::build(&[]);
+    let index = Index::build(&[]).unwrap();
     let results = index.search(None, "foo", 10).unwrap();
     assert_eq!(results.len(), 0);
   }
 
   #[test]
   fn search_invalid_scope_id() {
-    let index = Index::build(&[("foo.bar", 0), ("foo.baz", 1)]);
+    let index = Index::build(&[("foo.bar", 0), ("foo.baz", 1)]).unwrap();
     let results = index.search(Some(99), "foo", 10).unwrap();
     // No entries with scope_id 99 should exist
     assert_eq!(results.len(), 0);
@@ -462,28 +844,28 @@ mod tests {
 
   #[test]
   fn get_idx_by_name_empty_index() {
-    let index = Index::build(&[]);
+    let index = Index::build(&[]).unwrap();
     let idx = index.get_idx_by_name(0, "foo.bar");
     assert_eq!(idx, None);
   }
 
   #[test]
   fn get_idx_by_name_nonexistent() {
-    let index = Index::build(&[("foo.bar", 0)]);
+    let index = Index::build(&[("foo.bar", 0)]).unwrap();
     let idx = index.get_idx_by_name(0, "foo.baz");
     assert_eq!(idx, None);
   }
 
   #[test]
   fn get_idx_by_name_wrong_scope() {
-    let index = Index::build(&[("foo.bar", 0)]);
+    let index = Index::build(&[("foo.bar", 0)]).unwrap();
     let idx = index.get_idx_by_name(1, "foo.bar");
     assert_eq!(idx, None);
   }
 
   #[test]
   fn resolve_reference_out_of_bounds() {
-    let index = Index::build(&[("foo.bar", 0)]);
+    let index = Index::build(&[("foo.bar", 0)]).unwrap();
     let invalid_ref = LabelReference(999);
     let result = index.resolve_reference(invalid_ref);
     assert!(result.is_err());
@@ -491,40 +873,237 @@ mod tests {
 
   #[test]
   fn pascal_string_length_validation() {
-    // Valid: 255 bytes is the maximum
-    let long_string = "a".repeat(255);
+    let long_string = "a".repeat(256);
     let ps: PascalString = long_string.as_str().into();
-    assert_eq!(ps.data.len(), 255);
+    assert_eq!(ps.data.len(), 256);
 
-    // Valid: Convert from String
-    let long_string_owned = "b".repeat(255);
+    let long_string_owned = "b".repeat(70_000);
     let ps: PascalString = long_string_owned.into();
-    assert_eq!(ps.data.len(), 255);
+    assert_eq!(ps.data.len(), 70_000);
   }
 
   #[test]
-  #[should_panic(expected = "PascalString data exceeds maximum length")]
-  fn pascal_string_exceeds_max_length_from_str() {
-    let too_long_string = "a".repeat(256);
-    let _ps: PascalString = too_long_string.as_str().into();
+  fn write_read_roundtrip_long_names() {
+    let long_label = format!("foo.{}", "a".repeat(300));
+    let many_labels = vec!["b"; 300].join(".");
+    let index = Index::build(&[(long_label.as_str(), 0), (many_labels.as_str(), 0)]).unwrap();
+
+    let mut buf = Cursor::new(Vec::new());
+    index.write_into(&mut buf).unwrap();
+
+    let decoded = Index::read(buf.get_ref()).unwrap();
+    assert_eq!(index, decoded);
+    assert_eq!(decoded.get_idx_by_name(0, &long_label), Some(0));
+    assert_eq!(decoded.get_idx_by_name(0, &many_labels), Some(1));
+
+    let view = IndexView::read(buf.get_ref()).unwrap();
+    assert_eq!(view.get_idx_by_name(0, &long_label), Some(0));
+    assert_eq!(view.get_idx_by_name(0, &many_labels), Some(1));
   }
 
   #[test]
-  #[should_panic(expected = "PascalString data exceeds maximum length")]
-  fn pascal_string_exceeds_max_length_from_string() {
-    let too_long_string = "b".repeat(256);
-    let _ps: PascalString = too_long_string.into();
+  fn write_read_roundtrip_many_scopes() {
+    let names = (0..1000u32)
+      .map(|scope_id| format!("foo.bar{scope_id}"))
+      .collect::<Vec<_>>();
+    let entries = names
+      .iter()
+      .zip(0..)
+      .map(|(name, scope_id)| (name.as_str(), scope_id * 70_000))
+      .collect::<Vec<_>>();
+    let metadata = Metadata {
+      scopes: (0..1000).map(|scope_id| format!("scope {scope_id}")).collect(),
+      ..Metadata::default()
+    };
+    let index = Index::build(&entries).unwrap().with_metadata(metadata);
+
+    let mut buf = Cursor::new(Vec::new());
+    index.write_into(&mut buf).unwrap();
+
+    let decoded = Index::read(buf.get_ref()).unwrap();
+    assert_eq!(index, decoded);
+    assert_eq!(decoded.metadata().scope_name(999), Some("scope 999"));
+
+    let view = IndexView::read(buf.get_ref()).unwrap();
+    assert_eq!(view.metadata(), decoded.metadata());
+
+    for (idx, (name, scope_id)) in entries.iter().enumerate() {
+      assert_eq!(decoded.get_idx_by_name(*scope_id, name), Some(idx));
+      assert_eq!(view.get_idx_by_name(*scope_id, name), Some(idx));
+    }
+
+    let hits = view.search(Some(999 * 70_000), "bar", 10).unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].scope_id, 999 * 70_000);
+  }
+
+  #[test]
+  fn build_empty_label() {
+    for name in ["", "foo..bar", ".foo", "foo."] {
+      let result = Index::build(&[("foo.bar", 0), (name, 0)]);
+      assert!(
+        matches!(&result, Err(IxxError::InvalidName { name: invalid, .. }) if invalid == name),
+        "{name:?} was accepted"
+      );
+    }
   }
 
   #[test]
   fn index_size() {
-    let index = Index::build(&[("foo.bar", 0), ("foo.baz", 0), ("alpha.beta", 1)]);
+    let index = Index::build(&[("foo.bar", 0), ("foo.baz", 0), ("alpha.beta", 1)]).unwrap();
     assert_eq!(index.size(), 3);
   }
 
   #[test]
   fn index_size_empty() {
-    let index: Index = Index::build(&[]);
+    let index: Index = Index::build(&[]).unwrap();
     assert_eq!(index.size(), 0);
   }
+
+  fn names(index: &Index) -> Vec<(String, u32)> {
+    index
+      .entries()
+      .map(|(_, scope_id, name)| (name, scope_id))
+      .collect()
+  }
+
+  #[test]
+  fn merge() {
+    let metadata = Metadata {
+      scopes: vec!["NixOS".to_string(), "Home Manager".to_string()],
+      ..Metadata::default()
+    };
+    let nixos = Index::build(&[
+      ("services.nginx.enable", 0),
+      (r#"services.nginx.virtualHosts."example.com".root"#, 0),
+      ("programs.git.enable", 0),
+    ])
+    .unwrap()
+    .with_metadata(metadata.clone());
+    let home_manager = Index::build(&[("programs.git.enable", 1), ("services.nginx.enable", 0)]).unwrap();
+
+    let merged = nixos.merge(&home_manager).unwrap();
+    assert_eq!(merged.metadata(), &metadata);
+    assert_eq!(
+      names(&merged),
+      vec![
+        ("programs.git.enable".to_string(), 0),
+        ("programs.git.enable".to_string(), 1),
+        ("services.nginx.enable".to_string(), 0),
+        (r#"services.nginx.virtualHosts."example.com".root"#.to_string(), 0),
+      ]
+    );
+    // the label table only holds labels of the merged entries, most frequent first
+    assert_eq!(merged.labels.len(), 8);
+    assert_eq!(merged.labels[0].data, b"enable");
+    assert_eq!(merged.get_idx_by_name(1, "programs.git.enable"), Some(1));
+
+    let added = nixos
+      .with_added(&[("boot.loader.grub.enable", 0), ("programs.git.enable", 1)])
+      .unwrap();
+    assert_eq!(
+      added,
+      merged.with_added(&[("boot.loader.grub.enable", 0)]).unwrap()
+    );
+    assert_eq!(added.name_of(0).as_deref(), Some("boot.loader.grub.enable"));
+    assert!(nixos.with_added(&[("foo..bar", 0)]).is_err());
+  }
+
+  #[test]
+  fn merge_scopes_by_name() {
+    let metadata = |scopes: &[&str]| Metadata {
+      scopes: scopes.iter().map(ToString::to_string).collect(),
+      ..Metadata::default()
+    };
+    let index = Index::build(&[("programs.git.enable", 0), ("programs.git.enable", 1)])
+      .unwrap()
+      .with_metadata(metadata(&["NixOS", "Home Manager"]));
+    let other = Index::build(&[("programs.zsh.enable", 0), ("programs.fish.enable", 1)])
+      .unwrap()
+      .with_metadata(metadata(&["Home Manager", "nix-darwin"]));
+
+    let merged = index.merge(&other).unwrap();
+    assert_eq!(
+      merged.metadata(),
+      &metadata(&["NixOS", "Home Manager", "nix-darwin"])
+    );
+    assert_eq!(
+      names(&merged),
+      vec![
+        ("programs.fish.enable".to_string(), 2),
+        ("programs.git.enable".to_string(), 0),
+        ("programs.git.enable".to_string(), 1),
+        ("programs.zsh.enable".to_string(), 1),
+      ]
+    );
+  }
+
+  #[test]
+  fn with_scope_replaced() {
+    let metadata = |scopes: &[&str]| Metadata {
+      scopes: scopes.iter().map(ToString::to_string).collect(),
+      ..Metadata::default()
+    };
+    let index = Index::build(&[
+      ("programs.git.enable", 0),
+      ("programs.git.enable", 1),
+      ("programs.bash.enable", 1),
+    ])
+    .unwrap()
+    .with_metadata(metadata(&["NixOS", "Home Manager"]));
+    let reindexed = Index::build(&[("programs.zsh.enable", 0), ("programs.git.enable", 1)])
+      .unwrap()
+      .with_metadata(metadata(&["Home Manager", "NixOS"]));
+
+    let replaced = index.with_scope_replaced(1, &reindexed).unwrap();
+    assert_eq!(replaced.metadata(), index.metadata());
+    assert_eq!(
+      names(&replaced),
+      vec![
+        ("programs.git.enable".to_string(), 0),
+        ("programs.zsh.enable".to_string(), 1),
+      ]
+    );
+
+    // without scope names the ids have to match
+    let reindexed = Index::build(&[("programs.zsh.enable", 0), ("programs.fish.enable", 1)]).unwrap();
+    assert_eq!(
+      names(&index.with_scope_replaced(1, &reindexed).unwrap()),
+      vec![
+        ("programs.fish.enable".to_string(), 1),
+        ("programs.git.enable".to_string(), 0),
+      ]
+    );
+  }
+
+  #[test]
+  fn with_removed() {
+    let index = Index::build(&[
+      ("services.nginx.enable", 0),
+      (r#"services.nginx.virtualHosts."example.com".root"#, 0),
+      ("services.nginx.enable", 1),
+      ("services.caddy.enable", 0),
+    ])
+    .unwrap();
+
+    let removed = index
+      .with_removed(&[
+        ("services.nginx.enable", 0),
+        (r#"services.nginx.virtualHosts."example.com"."root""#, 0),
+        ("services.apache.enable", 0),
+      ])
+      .unwrap();
+    assert_eq!(
+      names(&removed),
+      vec![
+        ("services.nginx.enable".to_string(), 1),
+        ("services.caddy.enable".to_string(), 0)
+      ]
+    );
+    assert!(removed.labels.iter().all(|label| label.data != b"virtualHosts"));
+    // services.apache.enable was not in the index
+    assert_eq!(removed.size(), index.size() - 2);
+
+    assert_eq!(index.with_removed(&[]).unwrap(), index);
+  }
 }
@@ -1,13 +1,30 @@
+pub use diff::{DiffEntry, IndexDiff, MovedEntry};
 pub use error::IxxError;
 pub use index::Index;
+pub use lookup::Child;
+pub use metadata::{EntityKind, Metadata};
 pub use option::Option;
 pub use package::{License, Package, SourceProvenance};
+pub use search::{Score, SearchHit, SearchOptions, SearchPage};
+pub use view::IndexView;
 
+mod attr_path;
+mod diff;
 mod error;
 mod index;
+mod lookup;
+mod metadata;
 mod option;
 mod package;
+mod query;
+#[cfg(feature = "regex")]
+mod regex_search;
+mod search;
+mod storage;
 mod string_view;
+mod suggest;
+mod trigram;
+mod view;
 
 #[cfg(test)]
 mod test;
@@ -0,0 +1,341 @@
+use std::ops::Range;
+
+use crate::{
+  attr_path,
+  index::LabelReference,
+  storage::Storage,
+  string_view::{StringView, ignore_case_prefix},
+};
+
+/// A label directly below a prefix, see [`crate::Index::children`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Child {
+  pub label: String,
+  /// the prefix and this label as an attribute path, quoted where necessary like names in searches
+  pub path: String,
+  /// number of entries with the prefix and this label in front, including the child itself
+  pub entries: usize,
+  /// index of the entry named by the prefix and this label, if there is one
+  pub idx: Option<usize>,
+}
+
+/// Sorted views of the label and entry tables, built when an index is read or built, so names can be
+/// resolved with binary searches instead of scanning the whole index.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct Lookup {
+  /// label ids, ordered by label content
+  labels: Vec<u32>,
+  /// entry ids, ordered by scope id, then label references, then entry id
+  entries: Vec<u32>,
+  /// labels standing in for any attribute name, like `<name>` or `*`
+  placeholders: Vec<LabelReference>,
+}
+
+impl Lookup {
+  pub(crate) fn new<'a>(
+    labels: impl Iterator<Item = &'a [u8]>,
+    entries: impl Iterator<Item = (u32, &'a [LabelReference])>,
+  ) -> Self {
+    let labels = labels.collect::<Vec<_>>();
+    let placeholders = (0..labels.len() as u64)
+      .filter(|idx| attr_path::is_placeholder(labels[*idx as usize]))
+      .map(LabelReference)
+      .collect();
+    let mut sorted_labels = (0..labels.len() as u32).collect::<Vec<_>>();
+    sorted_labels.sort_by_key(|idx| labels[*idx as usize]);
+
+    // stable, so duplicate entries keep their index order
+    let entries = entries.collect::<Vec<_>>();
+    let mut sorted_entries = (0..entries.len() as u32).collect::<Vec<_>>();
+    sorted_entries.sort_by_key(|idx| entries[*idx as usize]);
+
+    Self {
+      labels: sorted_labels,
+      entries: sorted_entries,
+      placeholders,
+    }
+  }
+
+  pub(crate) fn label(&self, storage: &impl Storage, label: &[u8]) -> Option<LabelReference> {
+    let idx = self
+      .labels
+      .binary_search_by(|idx| storage.label(*idx as usize).unwrap_or_default().cmp(label))
+      .ok()?;
+
+    Some(LabelReference(u64::from(self.labels[idx])))
+  }
+
+  /// Returns the first entry with the given scope id and labels.
+  pub(crate) fn entry(
+    &self,
+    storage: &impl Storage,
+    scope_id: u32,
+    labels: &[LabelReference],
+  ) -> Option<usize> {
+    let key = Some((scope_id, labels));
+
+    let idx = self
+      .entries
+      .partition_point(|idx| storage.entry(*idx as usize) < key);
+
+    let entry_idx = *self.entries.get(idx)? as usize;
+
+    (storage.entry(entry_idx) == key).then_some(entry_idx)
+  }
+
+  /// Returns the range of `self.entries` with the given scope id whose labels start with `prefix`.
+  fn prefixed(&self, storage: &impl Storage, scope_id: u32, prefix: &[LabelReference]) -> Range<usize> {
+    let key = |idx: &u32| {
+      storage
+        .entry(*idx as usize)
+        .map(|(scope, labels)| (scope, &labels[..labels.len().min(prefix.len())]))
+    };
+
+    let start = self
+      .entries
+      .partition_point(|idx| key(idx) < Some((scope_id, prefix)));
+    let end = self
+      .entries
+      .partition_point(|idx| key(idx) <= Some((scope_id, prefix)));
+    start..end
+  }
+
+  /// Returns the first entry with the given scope id whose labels either equal `labels` or are
+  /// placeholders, preferring equal labels over placeholders from left to right.
+  ///
+  /// `None` in `labels` stands for a label which is not part of the label table.
+  fn declaration(
+    &self,
+    storage: &impl Storage,
+    scope_id: u32,
+    labels: &[Option<LabelReference>],
+  ) -> Option<usize> {
+    let start = self.entries.partition_point(|idx| {
+      storage
+        .entry(*idx as usize)
+        .is_some_and(|(scope, _)| scope < scope_id)
+    });
+    let end = self.entries.partition_point(|idx| {
+      storage
+        .entry(*idx as usize)
+        .is_some_and(|(scope, _)| scope <= scope_id)
+    });
+
+    self.declaration_in(storage, labels, 0, start..end)
+  }
+
+  /// `range` of `self.entries` contains all entries matching the first `depth` labels.
+  fn declaration_in(
+    &self,
+    storage: &impl Storage,
+    labels: &[Option<LabelReference>],
+    depth: usize,
+    range: Range<usize>,
+  ) -> Option<usize> {
+    let Some(label) = labels.get(depth) else {
+      // shorter entries are ordered first, so this is the only candidate left
+      let entry_idx = *self.entries.get(range.start)? as usize;
+      let (_, entry_labels) = storage.entry(entry_idx)?;
+      return (!range.is_empty() && entry_labels.len() == depth).then_some(entry_idx);
+    };
+
+    let candidates = label.iter().chain(
+      self
+        .placeholders
+        .iter()
+        .filter(|placeholder| Some(**placeholder) != *label),
+    );
+
+    for candidate in candidates {
+      let label_at_depth = |idx: &u32| {
+        storage
+          .entry(*idx as usize)
+          .and_then(|(_, entry_labels)| entry_labels.get(depth).copied())
+      };
+      let entries = &self.entries[range.clone()];
+      let start = range.start + entries.partition_point(|idx| label_at_depth(idx) < Some(*candidate));
+      let end = range.start + entries.partition_point(|idx| label_at_depth(idx) <= Some(*candidate));
+
+      if start < end
+        && let Some(entry_idx) = self.declaration_in(storage, labels, depth + 1, start..end)
+      {
+        return Some(entry_idx);
+      }
+    }
+
+    None
+  }
+}
+
+pub(crate) fn get_idx_by_name(storage: &impl Storage, scope_id: u32, name: &str) -> Option<usize> {
+  let lookup = storage.lookup();
+
+  let labels = attr_path::parse(name)
+    .ok()?
+    .iter()
+    .map(|label| lookup.label(storage, label.as_bytes()))
+    .collect::<Option<Vec<_>>>()?;
+
+  lookup.entry(storage, scope_id, &labels)
+}
+
+/// Resolves a path from a configuration, like `services.nginx.virtualHosts.example.root`, to the entry
+/// declaring it, like `services.nginx.virtualHosts.<name>.root`.
+pub(crate) fn get_idx_by_config_path(storage: &impl Storage, scope_id: u32, path: &str) -> Option<usize> {
+  let lookup = storage.lookup();
+
+  let labels = attr_path::parse(path)
+    .ok()?
+    .iter()
+    .map(|label| lookup.label(storage, label.as_bytes()))
+    .collect::<Vec<_>>();
+
+  lookup.declaration(storage, scope_id, &labels)
+}
+
+/// Returns the name of the entry at `idx`, or `None` if there is no such entry or its labels are
+/// missing from the label table.
+pub(crate) fn name_of(storage: &impl Storage, idx: usize) -> Option<String> {
+  let (_, labels) = storage.entry(idx)?;
+  if labels.iter().any(|label| storage.resolve(*label).is_err()) {
+    return None;
+  }
+
+  Some(StringView::from((storage, labels)).to_string())
+}
+
+/// Yields the index, scope id and name of every entry, skipping the ones [`name_of`] rejects.
+pub(crate) fn entries(storage: &impl Storage) -> impl Iterator<Item = (usize, u32, String)> {
+  (0..storage.entry_count()).filter_map(|idx| {
+    let (scope_id, _) = storage.entry(idx)?;
+    Some((idx, scope_id, name_of(storage, idx)?))
+  })
+}
+
+pub(crate) fn children(storage: &impl Storage, scope_id: u32, prefix: &str) -> Vec<Child> {
+  let lookup = storage.lookup();
+
+  let labels = if prefix.is_empty() {
+    Vec::new()
+  } else {
+    let Some(labels) = attr_path::parse(prefix).ok().and_then(|labels| {
+      labels
+        .iter()
+        .map(|label| lookup.label(storage, label.as_bytes()))
+        .collect::<Option<Vec<_>>>()
+    }) else {
+      return Vec::new();
+    };
+    labels
+  };
+
+  let mut children = labels_below(storage, scope_id, &labels)
+    .into_iter()
+    .map(|(_, child)| child)
+    .collect::<Vec<_>>();
+  children.sort_by(|a, b| a.label.cmp(&b.label));
+  children
+}
+
+/// Completes the last label of the attribute path `partial` to the labels below the ones in front of
+/// it, returning up to `max_results` full paths, most frequent labels first.
+///
+/// The last label is matched as a prefix ignoring case and may be an unterminated quote.
+pub(crate) fn complete(
+  storage: &impl Storage,
+  scope_id: u32,
+  partial: &str,
+  max_results: usize,
+) -> Vec<String> {
+  let lookup = storage.lookup();
+
+  let mut labels = Vec::new();
+  let mut rest = partial;
+  let last = loop {
+    let Ok((label, _, remainder)) = attr_path::next_label(rest, b".", true) else {
+      unreachable!("lenient parsing never fails");
+    };
+    match remainder.strip_prefix('.') {
+      Some(remainder) => {
+        let Some(label) = lookup.label(storage, label.as_bytes()) else {
+          return Vec::new();
+        };
+        labels.push(label);
+        rest = remainder;
+      }
+      None => break label,
+    }
+  };
+
+  let mut children = labels_below(storage, scope_id, &labels)
+    .into_iter()
+    .filter(|(_, child)| ignore_case_prefix(child.label.as_bytes(), last.as_bytes()).is_some())
+    .collect::<Vec<_>>();
+  // `Index::build` numbers labels by how often they occur
+  children.sort_by_key(|(label, _)| *label);
+
+  children
+    .into_iter()
+    .take(max_results)
+    .map(|(_, child)| child.path)
+    .collect()
+}
+
+/// Groups the entries with the given scope id starting with `prefix` by their next label.
+fn labels_below(
+  storage: &impl Storage,
+  scope_id: u32,
+  prefix: &[LabelReference],
+) -> Vec<(LabelReference, Child)> {
+  let lookup = storage.lookup();
+  let depth = prefix.len();
+
+  let mut rendered_prefix = String::new();
+  for label in prefix {
+    let label = String::from_utf8_lossy(storage.resolve(*label).unwrap_or_default());
+    attr_path::write_label(&mut rendered_prefix, &label).expect("writing to a String never fails");
+    rendered_prefix.push('.');
+  }
+
+  // entries below the prefix are ordered by their next label, so every child is a run of entries
+  let mut children: Vec<(LabelReference, Child)> = Vec::new();
+  for entry_idx in &lookup.entries[lookup.prefixed(storage, scope_id, prefix)] {
+    let Some((_, entry_labels)) = storage.entry(*entry_idx as usize) else {
+      continue;
+    };
+    let Some(label) = entry_labels.get(depth) else {
+      // the prefix itself
+      continue;
+    };
+
+    match children.last_mut() {
+      Some((last, child)) if last == label => child.entries += 1,
+      _ => {
+        let Ok(name) = storage.resolve(*label) else {
+          continue;
+        };
+        let label_str = String::from_utf8_lossy(name).into_owned();
+        let mut path = rendered_prefix.clone();
+        attr_path::write_label(&mut path, &label_str).expect("writing to a String never fails");
+
+        children.push((
+          *label,
+          Child {
+            label: label_str,
+            path,
+            entries: 1,
+            idx: None,
+          },
+        ));
+      }
+    }
+
+    let (_, child) = children.last_mut().expect("a child was just added");
+    // shorter entries are ordered first, so this is the first entry named like the child
+    if entry_labels.len() == depth + 1 && child.idx.is_none() {
+      child.idx = Some(*entry_idx as usize);
+    }
+  }
+
+  children
+}
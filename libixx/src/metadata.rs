@@ -0,0 +1,90 @@
+use binrw::binrw;
+use serde::Serialize;
+
+use crate::index::{PascalString, VarInt};
+
+/// Format revision written by this version of libixx.
+pub(crate) const FORMAT_VERSION: u8 = 3;
+
+/// Header of an index file, describing how it was built.
+///
+/// Indices in the `ixx02` format have no header, for them everything but `format_version` is unknown
+/// and left at its default.
+#[binrw]
+#[br(import(format_version: u8))]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Metadata {
+  /// format revision the index was read from, indices are always written in the latest one
+  #[br(calc = format_version)]
+  #[bw(ignore)]
+  pub format_version: u8,
+  /// number of entries per chunk file, `0` if unknown
+  pub chunk_size: u32,
+  pub kind: EntityKind,
+  /// seconds since the unix epoch, `0` if unknown
+  pub build_timestamp: u64,
+  /// name and version of the program which built the index
+  #[br(args(format_version), try_map = |generator: PascalString| String::try_from(generator))]
+  #[bw(map = |generator: &String| PascalString::from(generator.as_str()))]
+  pub generator: String,
+  #[bw(calc = VarInt(scopes.len() as u64))]
+  scope_count: VarInt,
+  /// scope names, the scope id is the position in this Vec
+  #[br(count = scope_count.0, args { inner: (format_version,) }, try_map = |scopes: Vec<PascalString>| scopes.into_iter().map(String::try_from).collect())]
+  #[bw(map = |scopes: &Vec<String>| scopes.iter().map(|scope| PascalString::from(scope.as_str())).collect::<Vec<_>>())]
+  pub scopes: Vec<String>,
+}
+
+#[binrw]
+#[brw(repr = u8)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EntityKind {
+  #[default]
+  Unknown = 0,
+  Options = 1,
+  Packages = 2,
+}
+
+impl Metadata {
+  pub(crate) fn legacy() -> Self {
+    Self {
+      format_version: 2,
+      ..Self::default()
+    }
+  }
+
+  /// Returns the name of the scope with the given id, if it has one.
+  #[must_use]
+  pub fn scope_name(&self, scope_id: u32) -> Option<&str> {
+    self
+      .scopes
+      .get(usize::try_from(scope_id).ok()?)
+      .map(String::as_str)
+      .filter(|name| !name.is_empty())
+  }
+
+  /// Returns the id of the scope with the given name, ignoring ASCII case.
+  #[must_use]
+  pub fn scope_id(&self, name: &str) -> Option<u32> {
+    self
+      .scopes
+      .iter()
+      .position(|scope| !scope.is_empty() && scope.eq_ignore_ascii_case(name))
+      .and_then(|idx| u32::try_from(idx).ok())
+  }
+}
+
+impl Default for Metadata {
+  fn default() -> Self {
+    Self {
+      format_version: FORMAT_VERSION,
+      chunk_size: 0,
+      kind: EntityKind::default(),
+      build_timestamp: 0,
+      generator: String::new(),
+      scopes: Vec::new(),
+    }
+  }
+}
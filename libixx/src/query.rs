@@ -0,0 +1,364 @@
+use std::borrow::Cow;
+
+use crate::attr_path;
+
+/// Parsed search query: whitespace separated terms, which all have to match a name, in any order.
+///
+/// - `-term` excludes names matching the term
+/// - `a|b` matches names matching any of the alternatives
+/// - `scope:<name>` matches names in the scope with that name or id
+/// - `^` and `$` anchor a pattern to the start and end of the name
+/// - `*` splits a pattern into segments, which have to match in order with anything in between
+/// - `.` splits segments into query labels, which are found inside of the labels of the name
+/// - `=label` has to equal a whole label of the name
+///
+/// Labels containing any of these characters have to be quoted like in attribute paths.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct Query<'a> {
+  pub(crate) terms: Vec<Term<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Term<'a> {
+  pub(crate) negated: bool,
+  pub(crate) alternatives: Vec<Alternative<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Alternative<'a> {
+  Pattern(Pattern<'a>),
+  Scope(Cow<'a, str>),
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct Pattern<'a> {
+  /// the first query label has to match at the start of the name
+  pub(crate) anchored_start: bool,
+  /// the last query label has to match at the end of the name
+  pub(crate) anchored_end: bool,
+  pub(crate) segments: Vec<Vec<Part<'a>>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Part<'a> {
+  pub(crate) label: Cow<'a, str>,
+  /// has to equal a whole label of the name instead of being found inside of one
+  pub(crate) exact: bool,
+}
+
+const WHITESPACE: &[u8] = b" \t\n\r";
+
+impl<'a> Query<'a> {
+  /// Parses `query`, failing with the reason if it is malformed.
+  ///
+  /// Unterminated quotes extend to the end of the query and empty labels are skipped, as queries are
+  /// usually incomplete while they are typed.
+  pub(crate) fn parse(query: &'a str) -> Result<Self, &'static str> {
+    let mut terms = Vec::new();
+    let mut rest = skip_whitespace(query);
+
+    while !rest.is_empty() {
+      let (term, remainder) = Term::parse(rest)?;
+      terms.extend(term);
+      rest = skip_whitespace(remainder);
+    }
+
+    Ok(Self { terms })
+  }
+}
+
+impl<'a> Term<'a> {
+  /// Returns `None` for terms matching every name, like `*`.
+  fn parse(input: &'a str) -> Result<(Option<Self>, &'a str), &'static str> {
+    let (negated, mut rest) = match input.strip_prefix('-') {
+      Some(rest) => (true, rest),
+      None => (false, input),
+    };
+    if negated && is_term_end(rest) {
+      return Err("nothing to exclude");
+    }
+
+    let mut alternatives = Vec::new();
+    loop {
+      let (alternative, remainder) = Alternative::parse(rest)?;
+      alternatives.push(alternative);
+
+      match remainder.strip_prefix('|') {
+        Some(remainder) => rest = remainder,
+        None => {
+          rest = remainder;
+          break;
+        }
+      }
+    }
+
+    let matches_everything = alternatives.iter().any(|alternative| match alternative {
+      Alternative::Pattern(pattern) => pattern.segments.is_empty(),
+      Alternative::Scope(_) => false,
+    });
+    if matches_everything {
+      return if negated {
+        Err("nothing to exclude")
+      } else {
+        Ok((None, rest))
+      };
+    }
+
+    Ok((
+      Some(Self {
+        negated,
+        alternatives,
+      }),
+      rest,
+    ))
+  }
+}
+
+impl<'a> Alternative<'a> {
+  fn parse(input: &'a str) -> Result<(Self, &'a str), &'static str> {
+    if is_alternative_end(input) {
+      return Err("empty alternative");
+    }
+
+    if let Some(name) = input.strip_prefix("scope:") {
+      let (name, quoted, rest) = lenient_label(name, b" \t\n\r|");
+      if name.is_empty() && !quoted {
+        return Err("missing scope name");
+      }
+      return Ok((Self::Scope(name), rest));
+    }
+
+    let (pattern, rest) = Pattern::parse(input)?;
+    Ok((Self::Pattern(pattern), rest))
+  }
+}
+
+impl<'a> Pattern<'a> {
+  fn parse(input: &'a str) -> Result<(Self, &'a str), &'static str> {
+    let (mut anchored_start, mut rest) = match input.strip_prefix('^') {
+      Some(rest) => (true, rest),
+      None => (false, input),
+    };
+    let mut anchored_end = false;
+    let mut segments = vec![Vec::new()];
+
+    loop {
+      let (exact, input) = match rest.strip_prefix('=') {
+        Some(input) => (true, input),
+        None => (false, rest),
+      };
+
+      let (label, _, remainder) = lenient_label(input, b".*^$| \t\n\r");
+      if !label.is_empty() {
+        segments
+          .last_mut()
+          .expect("there is always a segment")
+          .push(Part { label, exact });
+      } else if exact {
+        return Err("missing label after =");
+      }
+
+      rest = &remainder[remainder.len().min(1)..];
+      match remainder.as_bytes().first() {
+        Some(b'.') => {}
+        Some(b'*') => segments.push(Vec::new()),
+        Some(b'$') if is_alternative_end(rest) => {
+          anchored_end = true;
+          break;
+        }
+        Some(b'^' | b'$') => return Err("misplaced anchor"),
+        _ => {
+          rest = remainder;
+          break;
+        }
+      }
+    }
+
+    // a leading or trailing `*` allows anything before or after the pattern again
+    anchored_start &= segments.first().is_some_and(|segment| !segment.is_empty());
+    anchored_end &= segments.last().is_some_and(|segment| !segment.is_empty());
+    segments.retain(|segment| !segment.is_empty());
+
+    Ok((
+      Self {
+        anchored_start,
+        anchored_end,
+        segments,
+      },
+      rest,
+    ))
+  }
+
+  pub(crate) fn parts(&self) -> impl Iterator<Item = &Part<'a>> {
+    self.segments.iter().flatten()
+  }
+}
+
+fn lenient_label<'a>(input: &'a str, separators: &[u8]) -> (Cow<'a, str>, bool, &'a str) {
+  let Ok(label) = attr_path::next_label(input, separators, true) else {
+    unreachable!("lenient parsing never fails");
+  };
+  label
+}
+
+fn skip_whitespace(input: &str) -> &str {
+  input.trim_start_matches(|char: char| char.is_ascii() && WHITESPACE.contains(&(char as u8)))
+}
+
+fn is_term_end(input: &str) -> bool {
+  input
+    .as_bytes()
+    .first()
+    .is_none_or(|byte| WHITESPACE.contains(byte))
+}
+
+fn is_alternative_end(input: &str) -> bool {
+  is_term_end(input) || input.starts_with('|')
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn pattern(segments: &[&[&str]]) -> Pattern<'static> {
+    Pattern {
+      segments: segments
+        .iter()
+        .map(|segment| {
+          segment
+            .iter()
+            .map(|label| Part {
+              label: Cow::Owned(label.trim_start_matches('=').to_string()),
+              exact: label.starts_with('='),
+            })
+            .collect()
+        })
+        .collect(),
+      ..Pattern::default()
+    }
+  }
+
+  fn term(alternatives: Vec<Pattern<'static>>) -> Term<'static> {
+    Term {
+      negated: false,
+      alternatives: alternatives.into_iter().map(Alternative::Pattern).collect(),
+    }
+  }
+
+  fn parse(query: &str) -> Vec<Term<'_>> {
+    Query::parse(query).unwrap().terms
+  }
+
+  #[test]
+  fn terms() {
+    assert_eq!(
+      parse("nginx  enable"),
+      vec![
+        term(vec![pattern(&[&["nginx"]])]),
+        term(vec![pattern(&[&["enable"]])])
+      ]
+    );
+    assert_eq!(
+      parse("services.nginx*enable\thosts"),
+      vec![
+        term(vec![pattern(&[&["services", "nginx"], &["enable"]])]),
+        term(vec![pattern(&[&["hosts"]])])
+      ]
+    );
+    assert_eq!(
+      parse(r#"hosts."example com".root x"y z"#),
+      vec![
+        term(vec![pattern(&[&["hosts", "example com", "root"]])]),
+        term(vec![pattern(&[&[r#"x"y"#]])]),
+        term(vec![pattern(&[&["z"]])])
+      ]
+    );
+    assert_eq!(
+      parse(r#"."a \" b" c"#),
+      vec![
+        term(vec![pattern(&[&[r#"a " b"#]])]),
+        term(vec![pattern(&[&["c"]])])
+      ]
+    );
+    assert_eq!(
+      parse(r#"hosts."example.com"*root"#),
+      vec![term(vec![pattern(&[&["hosts", "example.com"], &["root"]])])]
+    );
+    // an unterminated quote while typing
+    assert_eq!(
+      parse(r#"hosts."exam"#),
+      vec![term(vec![pattern(&[&["hosts", "exam"]])])]
+    );
+    assert_eq!(parse(r#""a*b"x.c"#), vec![term(vec![pattern(&[&["a*bx", "c"]])])]);
+    // terms without labels match everything
+    assert_eq!(parse("foo.* * ^"), vec![term(vec![pattern(&[&["foo"]])])]);
+    assert_eq!(parse(""), vec![]);
+  }
+
+  #[test]
+  fn operators() {
+    assert_eq!(
+      parse(r#"-nginx|apache =enable scope:NixOS|scope:"Home Manager""#),
+      vec![
+        Term {
+          negated: true,
+          alternatives: vec![
+            Alternative::Pattern(pattern(&[&["nginx"]])),
+            Alternative::Pattern(pattern(&[&["apache"]]))
+          ]
+        },
+        term(vec![pattern(&[&["=enable"]])]),
+        Term {
+          negated: false,
+          alternatives: vec![
+            Alternative::Scope("NixOS".into()),
+            Alternative::Scope("Home Manager".into())
+          ]
+        }
+      ]
+    );
+    assert_eq!(
+      parse(r#"services.=nginx.="enable""#),
+      vec![term(vec![pattern(&[&["services", "=nginx", "=enable"]])])]
+    );
+    assert_eq!(
+      parse("^services*enable$ foo-bar a=b"),
+      vec![
+        term(vec![Pattern {
+          anchored_start: true,
+          anchored_end: true,
+          ..pattern(&[&["services"], &["enable"]])
+        }]),
+        term(vec![pattern(&[&["foo-bar"]])]),
+        term(vec![pattern(&[&["a=b"]])])
+      ]
+    );
+    assert_eq!(parse("^*enable*$"), vec![term(vec![pattern(&[&["enable"]])])]);
+    assert_eq!(
+      parse(r#"users."$user"$"#),
+      vec![term(vec![Pattern {
+        anchored_end: true,
+        ..pattern(&[&["users", "$user"]])
+      }])]
+    );
+  }
+
+  #[test]
+  fn errors() {
+    for (query, reason) in [
+      ("nginx -", "nothing to exclude"),
+      ("-*", "nothing to exclude"),
+      ("nginx||apache", "empty alternative"),
+      ("nginx|", "empty alternative"),
+      ("|nginx", "empty alternative"),
+      ("scope:", "missing scope name"),
+      ("scope:|x", "missing scope name"),
+      ("services.=", "missing label after ="),
+      ("= nginx", "missing label after ="),
+      ("nginx$enable", "misplaced anchor"),
+      ("services^nginx", "misplaced anchor"),
+    ] {
+      assert_eq!(Query::parse(query), Err(reason), "{query}");
+    }
+  }
+}
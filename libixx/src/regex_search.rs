@@ -0,0 +1,156 @@
+use std::fmt::Write;
+
+use regex::Regex;
+use regex_syntax::hir::{Hir, HirKind};
+
+use crate::{
+  IxxError, SearchHit, SearchPage,
+  search::{Ranking, Score},
+  storage::Storage,
+  string_view::StringView,
+};
+
+/// Finds names matching the regular expression `pattern` anywhere, as displayed with quoted labels.
+///
+/// Hits are ranked by depth and scope only. `pattern` is case sensitive unless it opts out with `(?i)`.
+pub(crate) fn search_regex(
+  storage: &impl Storage,
+  scope_id: Option<u32>,
+  pattern: &str,
+  offset: usize,
+  max_results: usize,
+) -> Result<SearchPage, IxxError> {
+  let regex = Regex::new(pattern)?;
+
+  // literals every match contains can only come from the labels of the name, so entries lacking them
+  // are ruled out before building their name
+  let trigrams = storage.trigrams();
+  let matching_labels = label_literals(pattern)
+    .iter()
+    .map(|literal| trigrams.matching_labels(storage, literal))
+    .collect::<Vec<_>>();
+
+  let mut ranking = Ranking::new(offset.saturating_add(max_results), storage.entry_count());
+  let mut total = 0;
+  let mut name = String::new();
+
+  for (idx, (entry_scope_id, labels)) in storage.entries().enumerate() {
+    if let Some(scope_id) = scope_id
+      && entry_scope_id != scope_id
+    {
+      continue;
+    }
+
+    let candidate = matching_labels.iter().all(|matching| {
+      labels
+        .iter()
+        .any(|label| matching.get(label.0 as usize).copied().unwrap_or(false))
+    });
+    if !candidate {
+      continue;
+    }
+
+    name.clear();
+    write!(name, "{}", StringView::from((storage, labels))).expect("writing to a String never fails");
+    if regex.is_match(&name) {
+      ranking.push(Score::base(labels.len(), entry_scope_id), idx, entry_scope_id);
+      total += 1;
+    }
+  }
+
+  let hits = ranking
+    .into_sorted_vec()
+    .into_iter()
+    .skip(offset)
+    .map(|(score, idx, scope_id)| {
+      let (_, labels) = storage.entry(idx).expect("the entry was yielded while searching");
+      let name = StringView::from((storage, labels)).to_string();
+      let matches = regex
+        .find_iter(&name)
+        .map(|found| found.range())
+        .filter(|range| !range.is_empty())
+        .collect();
+
+      Ok(SearchHit {
+        idx,
+        scope_id,
+        name,
+        score,
+        matches,
+      })
+    })
+    .collect::<Result<_, IxxError>>()?;
+
+  Ok(SearchPage { hits, total })
+}
+
+/// Pieces of the literals every match of `pattern` contains, which are found inside of a single label
+/// of the name.
+///
+/// Literals are split at `.` and quotes, as those separate labels in the displayed name, and pieces
+/// with escapes are skipped, as the escaped characters look different inside of the label.
+fn label_literals(pattern: &str) -> Vec<Vec<u8>> {
+  let Ok(hir) = regex_syntax::parse(pattern) else {
+    return Vec::new();
+  };
+
+  let mut literals = Vec::new();
+  required_literals(&hir, &mut literals);
+
+  literals
+    .iter()
+    .flat_map(|literal| literal.split(|byte| matches!(byte, b'.' | b'"')))
+    .filter(|piece| !piece.is_empty() && !piece.contains(&b'\\'))
+    .map(<[u8]>::to_vec)
+    .collect()
+}
+
+/// Collects the literals every match of `hir` contains.
+fn required_literals(hir: &Hir, literals: &mut Vec<Vec<u8>>) {
+  match hir.kind() {
+    HirKind::Literal(literal) => literals.push(literal.0.to_vec()),
+    HirKind::Capture(capture) => required_literals(&capture.sub, literals),
+    HirKind::Repetition(repetition) if repetition.min > 0 => {
+      required_literals(&repetition.sub, literals);
+    }
+    HirKind::Concat(subs) => {
+      for sub in subs {
+        required_literals(sub, literals);
+      }
+    }
+    // alternations, classes and optional parts do not have to be part of a match
+    _ => {}
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn literals() {
+    let literals = |pattern| {
+      label_literals(pattern)
+        .into_iter()
+        .map(|literal| String::from_utf8(literal).unwrap())
+        .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+      literals(r"^python3.*Packages\..*-bin$"),
+      vec!["python3", "Packages", "-bin"]
+    );
+    assert_eq!(
+      literals(r"services\.nginx\.enable"),
+      vec!["services", "nginx", "enable"]
+    );
+    assert_eq!(
+      literals(r#"hosts\."example\.com""#),
+      vec!["hosts", "example", "com"]
+    );
+    assert_eq!(literals(r"(foo)+(bar)?(baz|qux)*"), vec!["foo"]);
+    assert_eq!(literals(r"a|b"), Vec::<String>::new());
+    assert_eq!(literals(r#""\\\$user""#), Vec::<String>::new());
+    assert_eq!(literals(r"(?i)nginx"), Vec::<String>::new());
+  }
+}
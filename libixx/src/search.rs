@@ -0,0 +1,629 @@
+use std::{cmp::Reverse, collections::BinaryHeap, ops::Range};
+
+use crate::{
+  IxxError,
+  index::LabelReference,
+  query::{Alternative, Pattern, Query, Term},
+  storage::Storage,
+  string_view::{
+    Comparison, StringView, acronym_find, eq_ignore_case, ignore_case_find, is_char_boundary,
+    is_word_boundary, max_typos,
+  },
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchHit {
+  pub idx: usize,
+  pub scope_id: u32,
+  pub name: String,
+  pub score: Score,
+  /// sorted and non-overlapping byte ranges of `name` matched by the query labels
+  pub matches: Vec<Range<usize>>,
+}
+
+/// The hits of a search on one page of results, see [`crate::Index::search_page`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SearchPage {
+  /// best hits after skipping the ones on previous pages
+  pub hits: Vec<SearchHit>,
+  /// number of names matching the query, on any page
+  pub total: usize,
+}
+
+/// Optional behaviour of [`crate::Index::search_with`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SearchOptions {
+  /// Also find names where query labels only match with a few typos, like `servcies.nignx`. These rank
+  /// below all names matching the query as typed.
+  pub fuzzy: bool,
+  /// Only find names where query labels match with the same case as typed, instead of ignoring case.
+  /// Fuzzy matches still ignore case.
+  pub case_sensitive: bool,
+}
+
+impl SearchOptions {
+  /// How query labels are compared to find names matching them as typed.
+  fn verbatim(&self) -> Comparison {
+    if self.case_sensitive {
+      Comparison::CaseSensitive
+    } else {
+      Comparison::IgnoreCase
+    }
+  }
+}
+
+pub(crate) fn search(
+  storage: &impl Storage,
+  scope_id: Option<u32>,
+  query: &str,
+  offset: usize,
+  max_results: usize,
+  options: &SearchOptions,
+) -> Result<SearchPage, IxxError> {
+  let mut iter = SearchIter::new(storage, scope_id, query, options)?;
+
+  // the hits of previous pages have to be ranked as well, to know which ones to skip
+  let mut ranking = Ranking::new(offset.saturating_add(max_results), storage.entry_count());
+  let mut total = 0;
+
+  while let Some(found) = iter.next_match() {
+    let (score, idx, scope_id) = found?;
+    ranking.push(score, idx, scope_id);
+    total += 1;
+  }
+
+  let hits = ranking
+    .into_sorted_vec()
+    .into_iter()
+    .skip(offset)
+    .map(|(score, idx, scope_id)| iter.hit(score, idx, scope_id))
+    .collect::<Result<_, IxxError>>()?;
+
+  Ok(SearchPage { hits, total })
+}
+
+/// Entries matching a query, checked one by one in index order.
+pub(crate) struct SearchIter<'s, 'q, S> {
+  storage: &'s S,
+  scope_id: Option<u32>,
+  filters: Vec<Filter<'q>>,
+  fuzzy: bool,
+  verbatim: Comparison,
+  /// index of the next entry to check
+  next: usize,
+}
+
+impl<'s, 'q, S: Storage> SearchIter<'s, 'q, S> {
+  pub(crate) fn new(
+    storage: &'s S,
+    scope_id: Option<u32>,
+    query: &'q str,
+    options: &SearchOptions,
+  ) -> Result<Self, IxxError> {
+    let invalid_query = |reason| IxxError::InvalidQuery {
+      query: query.to_string(),
+      reason,
+    };
+    let filters = Query::parse(query)
+      .map_err(invalid_query)?
+      .terms
+      .into_iter()
+      .map(|term| Filter::new(storage, term, options.fuzzy))
+      .collect::<Result<Vec<_>, _>>()
+      .map_err(invalid_query)?;
+
+    Ok(Self {
+      storage,
+      scope_id,
+      filters,
+      fuzzy: options.fuzzy,
+      verbatim: options.verbatim(),
+      next: 0,
+    })
+  }
+
+  /// Returns the score, index and scope id of the next matching entry, without building its name.
+  fn next_match(&mut self) -> Option<Result<(Score, usize, u32), IxxError>> {
+    while self.next < self.storage.entry_count() {
+      let idx = self.next;
+      self.next += 1;
+
+      let Some((entry_scope_id, labels)) = self.storage.entry(idx) else {
+        continue;
+      };
+      if let Some(scope_id) = self.scope_id
+        && entry_scope_id != scope_id
+      {
+        continue;
+      }
+
+      match self.score(entry_scope_id, labels) {
+        Ok(Some(score)) => return Some(Ok((score, idx, entry_scope_id))),
+        Ok(None) => {}
+        Err(err) => return Some(Err(err)),
+      }
+    }
+
+    None
+  }
+
+  fn score(&self, scope_id: u32, labels: &[LabelReference]) -> Result<Option<Score>, IxxError> {
+    let entry_name = StringView::from((self.storage, labels));
+    let filters = &self.filters;
+
+    let (verbatim, patterns) =
+      if let Some(patterns) = matching_patterns(filters, &entry_name, scope_id, self.verbatim)? {
+        (true, patterns)
+      } else if self.fuzzy
+        && let Some(patterns) = matching_patterns(filters, &entry_name, scope_id, Comparison::Fuzzy)?
+      {
+        (false, patterns)
+      } else {
+        return Ok(None);
+      };
+
+    if is_excluded(filters, &entry_name, scope_id, self.verbatim)? {
+      return Ok(None);
+    }
+
+    let entry_labels = labels
+      .iter()
+      .map(|label| self.storage.resolve(*label))
+      .collect::<Result<Vec<_>, _>>()?;
+    Ok(Some(Score::new(&patterns, &entry_labels, scope_id, verbatim)))
+  }
+
+  /// Builds the hit for an entry [`Self::next_match`] returned.
+  fn hit(&self, score: Score, idx: usize, scope_id: u32) -> Result<SearchHit, IxxError> {
+    let (_, labels) = self
+      .storage
+      .entry(idx)
+      .expect("the entry was yielded while searching");
+    let entry_name = StringView::from((self.storage, labels));
+    let comparison = if score.verbatim {
+      self.verbatim
+    } else {
+      Comparison::Fuzzy
+    };
+    let patterns = matching_patterns(&self.filters, &entry_name, scope_id, comparison)?
+      .expect("the entry matched while searching");
+    let mut matches = Vec::new();
+    entry_name.find_matches(&patterns, comparison, &mut matches)?;
+
+    Ok(SearchHit {
+      idx,
+      scope_id,
+      name: entry_name.to_string(),
+      score,
+      matches,
+    })
+  }
+}
+
+impl<S: Storage> Iterator for SearchIter<'_, '_, S> {
+  type Item = Result<SearchHit, IxxError>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let found = self.next_match()?;
+    Some(found.and_then(|(score, idx, scope_id)| self.hit(score, idx, scope_id)))
+  }
+}
+
+/// Keeps the best scored entries seen so far.
+pub(crate) struct Ranking {
+  max_results: usize,
+  /// max-heap on the inverted score, so the worst of the kept results is always on top and can be
+  /// evicted once a better one shows up
+  results: BinaryHeap<(Reverse<Score>, usize, u32)>,
+}
+
+impl Ranking {
+  pub(crate) fn new(max_results: usize, entry_count: usize) -> Self {
+    Self {
+      max_results,
+      results: BinaryHeap::with_capacity(max_results.min(entry_count)),
+    }
+  }
+
+  /// Whether an entry with `score` would be kept.
+  fn accepts(&self, score: &Score) -> bool {
+    if self.results.len() < self.max_results {
+      return true;
+    }
+
+    self
+      .results
+      .peek()
+      .is_some_and(|(Reverse(worst), _, _)| score > worst)
+  }
+
+  pub(crate) fn push(&mut self, score: Score, idx: usize, scope_id: u32) {
+    if !self.accepts(&score) {
+      return;
+    }
+    if self.results.len() == self.max_results {
+      self.results.pop();
+    }
+
+    self.results.push((Reverse(score), idx, scope_id));
+  }
+
+  /// Returns the score, index and scope id of the kept entries, best first.
+  pub(crate) fn into_sorted_vec(self) -> Vec<(Score, usize, u32)> {
+    self
+      .results
+      .into_sorted_vec()
+      .into_iter()
+      .map(|(Reverse(score), idx, scope_id)| (score, idx, scope_id))
+      .collect()
+  }
+}
+
+/// A term of the query, prepared for checking it against every entry.
+struct Filter<'q> {
+  negated: bool,
+  alternatives: Vec<Matcher<'q>>,
+}
+
+enum Matcher<'q> {
+  Pattern {
+    pattern: Pattern<'q>,
+    /// labels containing each query label of the pattern, as every one of them has to be found inside
+    /// of some label of an entry, which is way cheaper to check once per label than for every entry
+    matching_labels: Vec<Vec<bool>>,
+    /// the same for fuzzy searches
+    fuzzy_matching_labels: Vec<Vec<bool>>,
+  },
+  Scope(u32),
+}
+
+impl<'q> Filter<'q> {
+  fn new(storage: &impl Storage, term: Term<'q>, fuzzy: bool) -> Result<Self, &'static str> {
+    let alternatives = term
+      .alternatives
+      .into_iter()
+      .map(|alternative| match alternative {
+        Alternative::Pattern(pattern) => {
+          let trigrams = storage.trigrams();
+          let label_masks = |fuzzy: bool| {
+            pattern
+              .parts()
+              .map(|part| {
+                let needle = part.label.as_bytes();
+                if fuzzy {
+                  trigrams.fuzzy_matching_labels(storage, needle, max_typos(needle.len()))
+                } else {
+                  trigrams.matching_labels(storage, needle)
+                }
+              })
+              .collect()
+          };
+
+          let matching_labels = label_masks(false);
+          // exclusions only ever apply to names matching as typed
+          let fuzzy_matching_labels = if fuzzy && !term.negated {
+            label_masks(true)
+          } else {
+            Vec::new()
+          };
+
+          Ok(Matcher::Pattern {
+            pattern,
+            matching_labels,
+            fuzzy_matching_labels,
+          })
+        }
+        Alternative::Scope(name) => storage
+          .metadata()
+          .scope_id(&name)
+          .or_else(|| name.parse().ok())
+          .map(Matcher::Scope)
+          .ok_or("unknown scope"),
+      })
+      .collect::<Result<_, _>>()?;
+
+    Ok(Self {
+      negated: term.negated,
+      alternatives,
+    })
+  }
+
+  /// Returns the first alternative matching the entry.
+  fn matching<S: Storage>(
+    &self,
+    entry_name: &StringView<'_, '_, S>,
+    scope_id: u32,
+    comparison: Comparison,
+  ) -> Result<Option<&Matcher<'q>>, IxxError> {
+    for alternative in &self.alternatives {
+      let matches = match alternative {
+        Matcher::Pattern {
+          pattern,
+          matching_labels,
+          fuzzy_matching_labels,
+        } => {
+          let matching_labels = if comparison == Comparison::Fuzzy {
+            fuzzy_matching_labels
+          } else {
+            matching_labels
+          };
+
+          matching_labels.iter().all(|matching| {
+            entry_name
+              .labels()
+              .iter()
+              .any(|label| matching.get(label.0 as usize).copied().unwrap_or(false))
+          }) && entry_name.matches(pattern, comparison)?
+        }
+        Matcher::Scope(id) => *id == scope_id,
+      };
+
+      if matches {
+        return Ok(Some(alternative));
+      }
+    }
+
+    Ok(None)
+  }
+}
+
+/// Returns the patterns which matched the entry for every term it has to match, or `None` if it does
+/// not match all of them.
+fn matching_patterns<'f, 'q, S: Storage>(
+  filters: &'f [Filter<'q>],
+  entry_name: &StringView<'_, '_, S>,
+  scope_id: u32,
+  comparison: Comparison,
+) -> Result<Option<Vec<&'f Pattern<'q>>>, IxxError> {
+  let mut patterns = Vec::new();
+
+  for filter in filters.iter().filter(|filter| !filter.negated) {
+    match filter.matching(entry_name, scope_id, comparison)? {
+      Some(Matcher::Pattern { pattern, .. }) => patterns.push(pattern),
+      Some(Matcher::Scope(_)) => {}
+      None => return Ok(None),
+    }
+  }
+
+  Ok(Some(patterns))
+}
+
+fn is_excluded<S: Storage>(
+  filters: &[Filter],
+  entry_name: &StringView<'_, '_, S>,
+  scope_id: u32,
+  comparison: Comparison,
+) -> Result<bool, IxxError> {
+  for filter in filters.iter().filter(|filter| filter.negated) {
+    if filter.matching(entry_name, scope_id, comparison)?.is_some() {
+      return Ok(true);
+    }
+  }
+
+  Ok(false)
+}
+
+/// Relevance of a search hit, higher is better.
+///
+/// Scores are compared field by field in declaration order, so every field only breaks ties of the
+/// fields above it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Score {
+  /// every query label was found as typed, not only with typos in a fuzzy search
+  pub verbatim: bool,
+  /// the query is a single pattern equal to the full name
+  pub exact_name: bool,
+  /// the last label of some query pattern equals the last label of the name
+  pub exact_last_label: bool,
+  /// query labels equal to a label of the name
+  pub exact_labels: u8,
+  /// query labels which are a prefix of a label of the name
+  pub prefix_labels: u8,
+  /// query labels which start or end at a word boundary inside a label of the name
+  pub boundary_labels: u8,
+  /// query labels which only match the first letters of words of a label of the name, like `eNRC`
+  pub acronym_labels: u8,
+  /// inverted label count, shallow names rank higher than deeply nested ones
+  pub shallowness: u8,
+  /// inverted scope id, scopes listed first in the config rank higher, all scopes from the 256th on
+  /// share the lowest priority
+  pub scope_priority: u8,
+}
+
+impl Score {
+  pub(crate) fn new(patterns: &[&Pattern], labels: &[&[u8]], scope_id: u32, verbatim: bool) -> Self {
+    let parts = patterns
+      .iter()
+      .flat_map(|pattern| pattern.parts())
+      .map(|part| part.label.as_bytes())
+      .collect::<Vec<_>>();
+
+    let exact_name = match patterns {
+      [pattern] => {
+        pattern.segments.len() == 1
+          && parts.len() == labels.len()
+          && parts
+            .iter()
+            .zip(labels)
+            .all(|(part, label)| eq_ignore_case(part, label))
+      }
+      _ => false,
+    };
+
+    let exact_last_label = labels.last().is_some_and(|label| {
+      patterns
+        .iter()
+        .filter_map(|pattern| pattern.parts().last())
+        .any(|part| eq_ignore_case(part.label.as_bytes(), label))
+    });
+
+    let mut score = Self {
+      verbatim,
+      exact_name,
+      exact_last_label,
+      ..Self::base(labels.len(), scope_id)
+    };
+
+    for part in parts {
+      match labels
+        .iter()
+        .map(|label| LabelMatch::new(part, label))
+        .max()
+        .unwrap_or(LabelMatch::None)
+      {
+        LabelMatch::Exact => score.exact_labels = score.exact_labels.saturating_add(1),
+        LabelMatch::Prefix => score.prefix_labels = score.prefix_labels.saturating_add(1),
+        LabelMatch::Boundary => score.boundary_labels = score.boundary_labels.saturating_add(1),
+        LabelMatch::Acronym => score.acronym_labels = score.acronym_labels.saturating_add(1),
+        LabelMatch::Inner | LabelMatch::None => {}
+      }
+    }
+
+    score
+  }
+
+  /// Score of a name matched as a whole, only ranked by its depth and scope.
+  pub(crate) fn base(label_count: usize, scope_id: u32) -> Self {
+    Self {
+      verbatim: true,
+      shallowness: u8::MAX - saturate(label_count),
+      scope_priority: u8::MAX - u8::try_from(scope_id).unwrap_or(u8::MAX),
+      ..Self::default()
+    }
+  }
+
+  /// Packs the score into a single number with the same ordering, for consumers which can only sort
+  /// by a number.
+  ///
+  /// Label counts are capped at 15, only whether there are acronym labels at all is kept.
+  #[must_use]
+  pub fn value(&self) -> u32 {
+    (u32::from(self.verbatim) << 31)
+      | (u32::from(self.exact_name) << 30)
+      | (u32::from(self.exact_last_label) << 29)
+      | (u32::from(self.exact_labels.min(15)) << 25)
+      | (u32::from(self.prefix_labels.min(15)) << 21)
+      | (u32::from(self.boundary_labels.min(15)) << 17)
+      | (u32::from(self.acronym_labels.min(1)) << 16)
+      | (u32::from(self.shallowness) << 8)
+      | u32::from(self.scope_priority)
+  }
+}
+
+/// How well a single query label matches a single label of a name, worst first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum LabelMatch {
+  None,
+  Inner,
+  Acronym,
+  Boundary,
+  Prefix,
+  Exact,
+}
+
+impl LabelMatch {
+  fn new(part: &[u8], label: &[u8]) -> Self {
+    if part.is_empty() {
+      return Self::None;
+    }
+
+    if eq_ignore_case(part, label) {
+      return Self::Exact;
+    }
+
+    let mut found = Self::None;
+    let mut from = 0;
+
+    while let Some(range) = ignore_case_find(&label[from..], part) {
+      let (start, end) = (from + range.start, from + range.end);
+
+      if start == 0 {
+        return Self::Prefix;
+      }
+
+      if is_word_boundary(label, start) || end == label.len() {
+        found = Self::Boundary;
+      } else if found == Self::None {
+        found = Self::Inner;
+      }
+
+      from = (start + 1..=label.len())
+        .find(|idx| is_char_boundary(label, *idx))
+        .expect("the end of the label is a char boundary");
+    }
+
+    // every letter of an acronym starts a word
+    if found == Self::None && acronym_find(label, 0, part).is_some() {
+      found = Self::Acronym;
+    }
+
+    found
+  }
+}
+
+fn saturate(value: usize) -> u8 {
+  u8::try_from(value).unwrap_or(u8::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn label_match() {
+    assert_eq!(LabelMatch::new(b"nginx", b"nginx"), LabelMatch::Exact);
+    assert_eq!(LabelMatch::new(b"nginx", b"NGINX"), LabelMatch::Exact);
+    assert_eq!(LabelMatch::new(b"nginx", b"nginx-unit"), LabelMatch::Prefix);
+    assert_eq!(LabelMatch::new(b"nginx", b"tengine-nginx"), LabelMatch::Boundary);
+    assert_eq!(LabelMatch::new(b"hosts", b"virtualHosts"), LabelMatch::Boundary);
+    assert_eq!(LabelMatch::new(b"host", b"virtualHosts"), LabelMatch::Boundary);
+    assert_eq!(LabelMatch::new(b"ost", b"virtualHosts"), LabelMatch::Inner);
+    assert_eq!(LabelMatch::new(b"foo", b"virtualHosts"), LabelMatch::None);
+    assert_eq!(
+      LabelMatch::new(b"eNRC", b"enableNixpkgsReleaseCheck"),
+      LabelMatch::Acronym
+    );
+    assert!(LabelMatch::Acronym < LabelMatch::Boundary);
+    // too short to be told apart from words
+    assert_eq!(LabelMatch::new(b"go", b"gitOrigin"), LabelMatch::None);
+    assert_eq!(LabelMatch::new(b"ui", b"userId"), LabelMatch::None);
+    assert_eq!(LabelMatch::new(b"gO", b"gitOrigin"), LabelMatch::Acronym);
+  }
+
+  #[test]
+  fn score_ordering() {
+    let query = Query::parse("nginx").unwrap();
+    let Alternative::Pattern(pattern) = &query.terms[0].alternatives[0] else {
+      unreachable!("the query is a plain label");
+    };
+    let search = [pattern];
+
+    let package = Score::new(&search, &[b"nginx"], 0, true);
+    let enable = Score::new(&search, &[b"services", b"nginx", b"enable"], 0, true);
+    let proxy_pass = Score::new(
+      &search,
+      &[
+        b"services",
+        b"nginx",
+        b"virtualHosts",
+        b"<name>",
+        b"locations",
+        b"<name>",
+        b"proxyPass",
+      ],
+      0,
+      true,
+    );
+    let unit = Score::new(&search, &[b"services", b"nginx-unit", b"enable"], 0, true);
+
+    assert!(package > enable);
+    assert!(enable > proxy_pass);
+    assert!(proxy_pass > unit);
+
+    assert!(package.value() > enable.value());
+    assert!(enable.value() > proxy_pass.value());
+    assert!(proxy_pass.value() > unit.value());
+
+    assert!(Score::new(&search, &[b"nginx"], 0, true) > Score::new(&search, &[b"nginx"], 1, true));
+  }
+}
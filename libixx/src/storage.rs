@@ -0,0 +1,35 @@
+use crate::{IxxError, Metadata, index::LabelReference, lookup::Lookup, trigram::Trigrams};
+
+/// Read access to the label and entry tables of an index, so searching and lookups work the same on
+/// an owned [`crate::Index`] and on an [`crate::IndexView`] over the raw bytes.
+pub(crate) trait Storage {
+  fn label_count(&self) -> usize;
+
+  fn label(&self, idx: usize) -> Option<&[u8]>;
+
+  fn entry_count(&self) -> usize;
+
+  /// scope id and labels of the entry
+  fn entry(&self, idx: usize) -> Option<(u32, &[LabelReference])>;
+
+  fn metadata(&self) -> &Metadata;
+
+  fn lookup(&self) -> &Lookup;
+
+  fn trigrams(&self) -> &Trigrams;
+
+  fn resolve(&self, reference: LabelReference) -> Result<&[u8], IxxError> {
+    usize::try_from(reference.0)
+      .ok()
+      .and_then(|idx| self.label(idx))
+      .ok_or(IxxError::InvalidLabelReference)
+  }
+
+  fn labels(&self) -> impl Iterator<Item = &[u8]> {
+    (0..self.label_count()).filter_map(|idx| self.label(idx))
+  }
+
+  fn entries(&self) -> impl Iterator<Item = (u32, &[LabelReference])> {
+    (0..self.entry_count()).filter_map(|idx| self.entry(idx))
+  }
+}
@@ -1,61 +1,162 @@
-use std::fmt::Display;
+use std::{
+  cmp::Reverse,
+  fmt::{Display, Write},
+  ops::Range,
+};
 
-use crate::{Index, IxxError, index::LabelReference};
+use crate::{IxxError, attr_path, index::LabelReference, query::Pattern, storage::Storage};
 
-pub struct StringView<'a, 'b> {
-  index: &'a Index,
+pub struct StringView<'a, 'b, S> {
+  index: &'a S,
   parts: &'b [LabelReference],
 }
 
-impl<'a, 'b> From<(&'a Index, &'b [LabelReference])> for StringView<'a, 'b> {
-  fn from((index, parts): (&'a Index, &'b [LabelReference])) -> Self {
+impl<'a, 'b, S> From<(&'a S, &'b [LabelReference])> for StringView<'a, 'b, S> {
+  fn from((index, parts): (&'a S, &'b [LabelReference])) -> Self {
     Self { index, parts }
   }
 }
 
-impl Display for StringView<'_, '_> {
+impl<S: Storage> Display for StringView<'_, '_, S> {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-    if self.parts.is_empty() {
-      return Ok(());
+    for (idx, part) in self.parts.iter().enumerate() {
+      if idx != 0 {
+        f.write_char('.')?;
+      }
+
+      let part = self.index.resolve(*part).ok().unwrap();
+      attr_path::write_label(f, std::str::from_utf8(part).unwrap())?;
     }
 
-    let part = self.index.resolve_reference(self.parts[0]).ok().unwrap();
+    Ok(())
+  }
+}
+
+impl<'b, S: Storage> StringView<'_, 'b, S> {
+  pub fn labels(&self) -> &'b [LabelReference] {
+    self.parts
+  }
+
+  /// Whether the query labels of `pattern` are found in the name, in order.
+  ///
+  /// Every query label is searched behind the bytes the previous one matched, so `foo*foo` needs two
+  /// occurrences of `foo` and `baz*bar` does not match the label `barbaz`.
+  pub fn matches(&self, pattern: &Pattern, comparison: Comparison) -> Result<bool, IxxError> {
+    self.walk_matches(pattern, comparison, |_, _| {})
+  }
 
-    write!(f, "{}", std::str::from_utf8(&part.data).unwrap())?;
+  /// Same as [`Self::matches`] for every one of `patterns`, but also records which bytes of the
+  /// displayed name matched the query labels, sorted and with overlaps of different patterns merged.
+  ///
+  /// `ranges` is cleared first and only complete if `true` is returned.
+  pub fn find_matches(
+    &self,
+    patterns: &[&Pattern],
+    comparison: Comparison,
+    ranges: &mut Vec<Range<usize>>,
+  ) -> Result<bool, IxxError> {
+    ranges.clear();
 
-    for part in &self.parts[1..] {
-      let part = self.index.resolve_reference(*part).ok().unwrap();
-      write!(f, ".{}", std::str::from_utf8(&part.data).unwrap())?;
+    let mut matches = Vec::new();
+    for pattern in patterns {
+      if !self.walk_matches(pattern, comparison, |label, range| matches.push((label, range)))? {
+        return Ok(false);
+      }
     }
+    matches.sort_by_key(|(label, range)| (*label, range.start));
 
-    Ok(())
+    // labels might be quoted in the displayed name, which shifts everything behind the quote
+    let mut label_idx = 0;
+    let mut label_offset = 0;
+    for (label, range) in matches {
+      while label_idx < label {
+        label_offset += attr_path::rendered_len(self.index.resolve(self.parts[label_idx])?) + 1;
+        label_idx += 1;
+      }
+
+      let label = self.index.resolve(self.parts[label])?;
+      let range = label_offset + attr_path::rendered_offset(label, range.start)
+        ..label_offset + attr_path::rendered_offset(label, range.end);
+
+      match ranges.last_mut() {
+        Some(last) if range.start < last.end => last.end = last.end.max(range.end),
+        _ => ranges.push(range),
+      }
+    }
+
+    Ok(true)
   }
-}
 
-impl StringView<'_, '_> {
-  pub fn matches(&self, search: &[Vec<&[u8]>]) -> Result<bool, IxxError> {
+  /// Calls `on_match` with the position of the label and the matched bytes inside of it for every query
+  /// label.
+  fn walk_matches(
+    &self,
+    pattern: &Pattern,
+    comparison: Comparison,
+    mut on_match: impl FnMut(usize, Range<usize>),
+  ) -> Result<bool, IxxError> {
     let mut self_parts_start = 0;
     let mut self_parts_start_str_idx = 0;
 
-    for segment in search {
-      for part in segment {
-        'outer: {
-          for (self_part_idx, self_part) in self.parts[self_parts_start..].iter().enumerate() {
-            let self_part = self.index.resolve_reference(*self_part)?;
-
-            if let Some(idx) = ascii_ignore_case_find(&self_part.data[self_parts_start_str_idx..], part) {
-              self_parts_start += self_part_idx;
-              if self_part_idx == 0 {
-                self_parts_start_str_idx += idx;
-              } else {
-                self_parts_start_str_idx = 0;
-              }
-              break 'outer;
-            }
-            self_parts_start_str_idx = 0;
-          }
+    let part_count = pattern.parts().count();
+    for (part_idx, part) in pattern.parts().enumerate() {
+      let at_start = part_idx == 0 && pattern.anchored_start;
+      let at_end = part_idx + 1 == part_count && pattern.anchored_end;
+
+      // only the last label can end the name
+      if at_end {
+        let last = self.parts.len().saturating_sub(1);
+        if self_parts_start < last {
+          self_parts_start = last;
+          self_parts_start_str_idx = 0;
+        }
+      }
+
+      loop {
+        let Some(self_part) = self.parts.get(self_parts_start) else {
+          return Ok(false);
+        };
+        if at_start && (self_parts_start, self_parts_start_str_idx) != (0, 0) {
           return Ok(false);
         }
+        let self_part = self.index.resolve(*self_part)?;
+
+        let found = if part.exact {
+          // whole labels can only be matched from their start
+          (self_parts_start_str_idx == 0)
+            .then(|| find(self_part, part.label.as_bytes(), true, true, comparison))
+            .flatten()
+        } else {
+          find(
+            &self_part[self_parts_start_str_idx..],
+            part.label.as_bytes(),
+            at_start,
+            at_end,
+            comparison,
+          )
+        };
+
+        if let Some(found) = found {
+          let start = self_parts_start_str_idx + found.start;
+          let end = self_parts_start_str_idx + found.end;
+          on_match(self_parts_start, start..end);
+          self_parts_start_str_idx = end;
+          break;
+        }
+
+        // acronyms mix the case of the words they abbreviate
+        if !(part.exact || at_start || at_end || comparison == Comparison::CaseSensitive)
+          && let Some(words) = acronym_find(self_part, self_parts_start_str_idx, part.label.as_bytes())
+        {
+          for word in &words {
+            on_match(self_parts_start, *word..*word + 1);
+          }
+          self_parts_start_str_idx = words.last().expect("acronyms have several letters") + 1;
+          break;
+        }
+
+        self_parts_start += 1;
+        self_parts_start_str_idx = 0;
       }
     }
 
@@ -63,6 +164,146 @@ impl StringView<'_, '_> {
   }
 }
 
+/// How query labels are compared to the labels of a name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+  /// ignoring case, see [`fold_case`]
+  IgnoreCase,
+  CaseSensitive,
+  /// ignoring ASCII case and allowing a few typos, see [`max_typos`]
+  Fuzzy,
+}
+
+/// Finds `needle` inside of `a`, at its very start or end if requested.
+fn find(
+  a: &[u8],
+  needle: &[u8],
+  at_start: bool,
+  at_end: bool,
+  comparison: Comparison,
+) -> Option<Range<usize>> {
+  match comparison {
+    Comparison::IgnoreCase => match (at_start, at_end) {
+      (false, false) => ignore_case_find(a, needle),
+      (true, false) => ignore_case_prefix(a, needle).map(|len| 0..len),
+      (false, true) => ignore_case_suffix(a, needle).map(|len| a.len() - len..a.len()),
+      (true, true) => eq_ignore_case(a, needle).then_some(0..a.len()),
+    },
+    Comparison::CaseSensitive => match (at_start, at_end) {
+      (false, false) if needle.is_empty() => None,
+      (false, false) => a
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|idx| idx..idx + needle.len()),
+      (true, false) => a.starts_with(needle).then_some(0..needle.len()),
+      (false, true) => a.ends_with(needle).then_some(a.len() - needle.len()..a.len()),
+      (true, true) => (a == needle).then_some(0..a.len()),
+    },
+    Comparison::Fuzzy => {
+      let max_distance = max_typos(needle.len());
+
+      match (at_start, at_end) {
+        (false, false) => fuzzy_find(a, needle, max_distance),
+        (true, true) => edit_distance(a, needle, max_distance).map(|_| 0..a.len()),
+        _ => (needle.len().saturating_sub(max_distance).max(1)..=(needle.len() + max_distance).min(a.len()))
+          .map(|len| if at_start { 0..len } else { a.len() - len..a.len() })
+          .filter(|range| is_char_boundary(a, range.start) && is_char_boundary(a, range.end))
+          .filter_map(|range| Some((edit_distance(&a[range.clone()], needle, max_distance)?, range)))
+          .min_by_key(|(distance, range)| (*distance, Reverse(range.len())))
+          .map(|(_, range)| range),
+      }
+    }
+  }
+}
+
+/// Maps `char` to its simple case folding, so chars differing only in case map to the same one.
+///
+/// This follows the lowercase mapping of chars which lower to a single char, the few others are kept.
+pub fn fold_case(char: char) -> char {
+  if char.is_ascii() {
+    return char.to_ascii_lowercase();
+  }
+
+  let mut lower = char.to_lowercase();
+  match (lower.next(), lower.next()) {
+    (Some(lower), None) => lower,
+    _ => char,
+  }
+}
+
+/// Finds `needle` inside of `a`, ignoring case. Only labels or queries with non-ASCII chars take the
+/// slow path over chars, as case folding can change their byte length.
+pub fn ignore_case_find(a: &[u8], needle: &[u8]) -> Option<Range<usize>> {
+  if a.is_ascii() && needle.is_ascii() {
+    return ascii_ignore_case_find(a, needle).map(|idx| idx..idx + needle.len());
+  }
+  if needle.is_empty() {
+    return None;
+  }
+
+  (0..a.len())
+    .filter(|idx| is_char_boundary(a, *idx))
+    .find_map(|idx| Some(idx..idx + ignore_case_prefix(&a[idx..], needle)?))
+}
+
+/// Length of the start of `a` equal to `needle`, ignoring case.
+pub fn ignore_case_prefix(a: &[u8], needle: &[u8]) -> Option<usize> {
+  if a.is_ascii() && needle.is_ascii() {
+    return (a.len() >= needle.len() && eq_ignore_ascii_case(&a[..needle.len()], needle))
+      .then_some(needle.len());
+  }
+
+  let (Ok(a), Ok(needle)) = (std::str::from_utf8(a), std::str::from_utf8(needle)) else {
+    return None;
+  };
+  let mut chars = a.char_indices();
+  for expected in needle.chars() {
+    let (_, char) = chars.next()?;
+    if fold_case(char) != fold_case(expected) {
+      return None;
+    }
+  }
+
+  Some(chars.next().map_or(a.len(), |(idx, _)| idx))
+}
+
+/// Length of the end of `a` equal to `needle`, ignoring case.
+pub fn ignore_case_suffix(a: &[u8], needle: &[u8]) -> Option<usize> {
+  if a.is_ascii() && needle.is_ascii() {
+    return (a.len() >= needle.len() && eq_ignore_ascii_case(&a[a.len() - needle.len()..], needle))
+      .then_some(needle.len());
+  }
+
+  let (Ok(a), Ok(needle)) = (std::str::from_utf8(a), std::str::from_utf8(needle)) else {
+    return None;
+  };
+  let mut chars = a.char_indices().rev();
+  let mut start = a.len();
+  for expected in needle.chars().rev() {
+    let (idx, char) = chars.next()?;
+    if fold_case(char) != fold_case(expected) {
+      return None;
+    }
+    start = idx;
+  }
+
+  Some(a.len() - start)
+}
+
+/// Whether `a` and `b` only differ in case, see [`fold_case`].
+pub fn eq_ignore_case(a: &[u8], b: &[u8]) -> bool {
+  if a.is_ascii() && b.is_ascii() {
+    return eq_ignore_ascii_case(a, b);
+  }
+
+  ignore_case_prefix(a, b) == Some(a.len())
+}
+
+/// Whether `idx` does not point into the middle of a UTF-8 encoded char of `bytes`.
+pub fn is_char_boundary(bytes: &[u8], idx: usize) -> bool {
+  bytes.get(idx).is_none_or(|byte| (*byte as i8) >= -0x40)
+}
+
 #[inline(always)]
 pub fn ascii_ignore_case_find(a: &[u8], needle: &[u8]) -> Option<usize> {
   let n = needle.len();
@@ -79,6 +320,123 @@ pub fn ascii_ignore_case_find(a: &[u8], needle: &[u8]) -> Option<usize> {
   None
 }
 
+/// How many typos a query label of `len` bytes may contain in fuzzy searches.
+pub fn max_typos(len: usize) -> usize {
+  match len {
+    0..=2 => 0,
+    3..=5 => 1,
+    _ => 2,
+  }
+}
+
+/// Finds the part of `a` closest to `needle`, ignoring ASCII case and allowing at most `max_distance`
+/// inserted, deleted, substituted or swapped adjacent bytes.
+///
+/// Of equally close parts the longest one wins, so no matching bytes are left out of it. Parts which do
+/// not start and end at char boundaries are skipped.
+pub fn fuzzy_find(a: &[u8], needle: &[u8], max_distance: usize) -> Option<Range<usize>> {
+  if needle.is_empty() || a.len() + max_distance < needle.len() {
+    return None;
+  }
+
+  // rows of the (optimal string alignment) edit distance between a prefix of the needle and a part of
+  // `a` ending at the column, along with where that part starts
+  let mut before_previous = Vec::new();
+  let mut previous = (0..=a.len()).map(|start| (0, start)).collect::<Vec<_>>();
+  let mut current = Vec::with_capacity(a.len() + 1);
+
+  for i in 1..=needle.len() {
+    current.clear();
+    current.push((i, 0));
+
+    for j in 1..=a.len() {
+      let (distance, start) = previous[j - 1];
+      let mut best = (
+        distance + usize::from(!eq_ignore_ascii_case_char(needle[i - 1], a[j - 1])),
+        start,
+      );
+
+      let (distance, start) = previous[j];
+      if distance + 1 < best.0 {
+        best = (distance + 1, start);
+      }
+
+      let (distance, start) = current[j - 1];
+      if distance + 1 < best.0 {
+        best = (distance + 1, start);
+      }
+
+      if i > 1
+        && j > 1
+        && eq_ignore_ascii_case_char(needle[i - 1], a[j - 2])
+        && eq_ignore_ascii_case_char(needle[i - 2], a[j - 1])
+      {
+        let (distance, start) = before_previous[j - 2];
+        if distance + 1 < best.0 {
+          best = (distance + 1, start);
+        }
+      }
+
+      current.push(best);
+    }
+
+    std::mem::swap(&mut before_previous, &mut previous);
+    std::mem::swap(&mut previous, &mut current);
+  }
+
+  previous
+    .iter()
+    .enumerate()
+    .filter(|(end, (distance, start))| {
+      *distance <= max_distance && start < end && is_char_boundary(a, *start) && is_char_boundary(a, *end)
+    })
+    .min_by_key(|(end, (distance, start))| (*distance, Reverse(end - start), *end))
+    .map(|(end, (_, start))| *start..end)
+}
+
+/// Edit distance between the whole of `a` and `b`, counted like in [`fuzzy_find`], or `None` if it
+/// exceeds `max_distance`.
+pub fn edit_distance(a: &[u8], b: &[u8], max_distance: usize) -> Option<usize> {
+  if a.len().abs_diff(b.len()) > max_distance {
+    return None;
+  }
+
+  let mut before_previous = Vec::new();
+  let mut previous = (0..=b.len()).collect::<Vec<_>>();
+  let mut current = Vec::with_capacity(b.len() + 1);
+
+  for i in 1..=a.len() {
+    current.clear();
+    current.push(i);
+
+    for j in 1..=b.len() {
+      let mut distance = (previous[j - 1] + usize::from(!eq_ignore_ascii_case_char(a[i - 1], b[j - 1])))
+        .min(previous[j] + 1)
+        .min(current[j - 1] + 1);
+
+      if i > 1
+        && j > 1
+        && eq_ignore_ascii_case_char(a[i - 1], b[j - 2])
+        && eq_ignore_ascii_case_char(a[i - 2], b[j - 1])
+      {
+        distance = distance.min(before_previous[j - 2] + 1);
+      }
+
+      current.push(distance);
+    }
+
+    // distances never shrink from one row to the next
+    if current.iter().all(|distance| *distance > max_distance) {
+      return None;
+    }
+
+    std::mem::swap(&mut before_previous, &mut previous);
+    std::mem::swap(&mut previous, &mut current);
+  }
+
+  Some(previous[b.len()]).filter(|distance| *distance <= max_distance)
+}
+
 #[inline(always)]
 pub fn eq_ignore_ascii_case(a: &[u8], b: &[u8]) -> bool {
   // the additional bounds check improved LLVM auto vectorization?
@@ -95,15 +453,71 @@ pub fn eq_ignore_ascii_case_char(a: u8, b: u8) -> bool {
     (( b'A' <= a && a <= b'Z') || (b'a' <= a && a <= b'z')))
 }
 
+/// Whether `needle` could be an acronym of camelCase or kebab-case words, like `eNRC`.
+///
+/// Two letters like `go` are too common inside of words to be taken as acronyms, unless their case is
+/// mixed like `hM`.
+pub fn is_acronym(needle: &[u8]) -> bool {
+  let mixed_case = needle.iter().any(u8::is_ascii_uppercase) && needle.iter().any(u8::is_ascii_lowercase);
+
+  (needle.len() >= 3 || (needle.len() == 2 && mixed_case)) && needle.iter().all(u8::is_ascii_alphanumeric)
+}
+
+/// Positions of the first letters of the words of `label`, see [`is_word_boundary`].
+pub fn word_starts(label: &[u8]) -> impl Iterator<Item = usize> + '_ {
+  (0..label.len()).filter(|idx| label[*idx].is_ascii_alphanumeric() && is_word_boundary(label, *idx))
+}
+
+/// Finds consecutive words of `label` starting at or after `from`, whose first letters spell `needle`,
+/// ignoring ASCII case, e.g. `eNRC` in `enableNixpkgsReleaseCheck`. Returns the positions of those
+/// letters.
+pub fn acronym_find(label: &[u8], from: usize, needle: &[u8]) -> Option<Vec<usize>> {
+  if !is_acronym(needle) {
+    return None;
+  }
+
+  let words = word_starts(label).filter(|idx| *idx >= from).collect::<Vec<_>>();
+  words
+    .windows(needle.len())
+    .find(|words| {
+      words
+        .iter()
+        .zip(needle)
+        .all(|(idx, letter)| eq_ignore_ascii_case_char(label[*idx], *letter))
+    })
+    .map(<[usize]>::to_vec)
+}
+
+/// Whether a new word starts at `idx` inside of `label`, e.g. after a `-` or at a camelCase hump.
+#[inline(always)]
+pub fn is_word_boundary(label: &[u8], idx: usize) -> bool {
+  if idx == 0 {
+    return true;
+  }
+
+  match (label.get(idx - 1), label.get(idx)) {
+    (Some(prev), Some(cur)) => {
+      matches!(prev, b'-' | b'_') || (prev.is_ascii_lowercase() && cur.is_ascii_uppercase())
+    }
+    _ => false,
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use crate::index::*;
+  use crate::query::{Alternative, Query};
   use crate::string_view::*;
 
   fn make_index_with_labels(labels: Vec<PascalString>) -> Index {
-    Index {
-      labels: labels,
-      entries: vec![],
+    Index::new(labels, vec![])
+  }
+
+  fn parse_pattern(query: &str) -> Pattern<'_> {
+    let mut query = Query::parse(query).unwrap();
+    match query.terms.pop().map(|mut term| term.alternatives.remove(0)) {
+      Some(Alternative::Pattern(pattern)) => pattern,
+      _ => Pattern::default(),
     }
   }
 
@@ -113,14 +527,14 @@ mod tests {
     let entry = vec![LabelReference(0), LabelReference(1)];
     let view = StringView::from((&index, entry.as_slice()));
     // Match both segments
-    let pattern = vec![vec![b"foo".as_ref()], vec![b"bar".as_ref()]];
-    assert!(view.matches(&pattern).unwrap());
+    let pattern = parse_pattern("foo*bar");
+    assert!(view.matches(&pattern, Comparison::IgnoreCase).unwrap());
     // Match only first segment
-    let pattern = vec![vec![b"foo".as_ref()]];
-    assert!(view.matches(&pattern).unwrap());
+    let pattern = parse_pattern("foo");
+    assert!(view.matches(&pattern, Comparison::IgnoreCase).unwrap());
     // No match
-    let pattern = vec![vec![b"baz".as_ref()]];
-    assert!(!view.matches(&pattern).unwrap());
+    let pattern = parse_pattern("baz");
+    assert!(!view.matches(&pattern, Comparison::IgnoreCase).unwrap());
   }
 
   #[test]
@@ -128,8 +542,8 @@ mod tests {
     let index = make_index_with_labels(vec!["Foo".into(), "Bar".into()]);
     let entry = vec![LabelReference(0), LabelReference(1)];
     let view = StringView::from((&index, entry.as_slice()));
-    let pattern = vec![vec![b"foo".as_ref()], vec![b"bar".as_ref()]];
-    assert!(view.matches(&pattern).unwrap());
+    let pattern = parse_pattern("foo*bar");
+    assert!(view.matches(&pattern, Comparison::IgnoreCase).unwrap());
   }
 
   #[test]
@@ -138,14 +552,162 @@ mod tests {
     let entry = vec![LabelReference(0)];
     let view = StringView::from((&index, entry.as_slice()));
     // Partial match
-    let pattern = vec![vec![b"foo".as_ref()]];
-    assert!(view.matches(&pattern).unwrap());
+    let pattern = parse_pattern("foo");
+    assert!(view.matches(&pattern, Comparison::IgnoreCase).unwrap());
     // Wildcard-like: match any segment
-    let pattern = vec![vec![b"ba".as_ref()]];
-    assert!(view.matches(&pattern).unwrap());
+    let pattern = parse_pattern("ba");
+    assert!(view.matches(&pattern, Comparison::IgnoreCase).unwrap());
     // No match
-    let pattern = vec![vec![b"qux".as_ref()]];
-    assert!(!view.matches(&pattern).unwrap());
+    let pattern = parse_pattern("qux");
+    assert!(!view.matches(&pattern, Comparison::IgnoreCase).unwrap());
+  }
+
+  #[test]
+  fn test_string_view_find_matches() {
+    let index = make_index_with_labels(vec!["home".into(), "enableNixpkgsReleaseCheck".into()]);
+    let entry = vec![LabelReference(0), LabelReference(1)];
+    let view = StringView::from((&index, entry.as_slice()));
+    let mut ranges = Vec::new();
+
+    let pattern = parse_pattern("ho*en*nix");
+    assert!(
+      view
+        .find_matches(&[&pattern], Comparison::IgnoreCase, &mut ranges)
+        .unwrap()
+    );
+    assert_eq!(ranges, vec![0..2, 5..7, 11..14]);
+
+    let pattern = parse_pattern("home.check");
+    assert!(
+      view
+        .find_matches(&[&pattern], Comparison::IgnoreCase, &mut ranges)
+        .unwrap()
+    );
+    assert_eq!(ranges, vec![0..4, 25..30]);
+
+    // every query label consumes what it matched
+    let pattern = parse_pattern("e*e*e");
+    assert!(
+      view
+        .find_matches(&[&pattern], Comparison::IgnoreCase, &mut ranges)
+        .unwrap()
+    );
+    assert_eq!(ranges, vec![3..4, 5..6, 10..11]);
+
+    let pattern = parse_pattern("check*home");
+    assert!(
+      !view
+        .find_matches(&[&pattern], Comparison::IgnoreCase, &mut ranges)
+        .unwrap()
+    );
+
+    let pattern = parse_pattern("home*eNRC");
+    assert!(
+      view
+        .find_matches(&[&pattern], Comparison::IgnoreCase, &mut ranges)
+        .unwrap()
+    );
+    assert_eq!(ranges, vec![0..4, 5..6, 11..12, 18..19, 25..26]);
+
+    let pattern = parse_pattern("hmoe*nixpgks");
+    assert!(
+      !view
+        .find_matches(&[&pattern], Comparison::IgnoreCase, &mut ranges)
+        .unwrap()
+    );
+    assert!(
+      view
+        .find_matches(&[&pattern], Comparison::Fuzzy, &mut ranges)
+        .unwrap()
+    );
+    assert_eq!(ranges, vec![0..4, 11..18]);
+  }
+
+  #[test]
+  fn test_string_view_matches_anchors_and_exact_labels() {
+    let index = make_index_with_labels(vec!["services".into(), "nginx-unit".into(), "enable".into()]);
+    let entry = vec![LabelReference(0), LabelReference(1), LabelReference(2)];
+    let view = StringView::from((&index, entry.as_slice()));
+    let matches = |query| {
+      view
+        .matches(&parse_pattern(query), Comparison::IgnoreCase)
+        .unwrap()
+    };
+
+    assert!(matches("^serv"));
+    assert!(!matches("^nginx"));
+    assert!(matches("able$"));
+    assert!(!matches("unit$"));
+    assert!(!matches("^services$"));
+    assert!(matches("^services*enable$"));
+    assert!(!matches("=nginx"));
+    assert!(matches("=NGINX-unit"));
+    assert!(matches("services.=enable$"));
+    assert!(!matches("=services*=services"));
+    assert!(matches("nU.e"));
+    assert!(!matches("^nU"));
+    // two letters of the same case are not taken as an acronym
+    assert!(!matches("nu.e"));
+
+    let matches_fuzzy = |query| view.matches(&parse_pattern(query), Comparison::Fuzzy).unwrap();
+    assert!(matches_fuzzy("^srevices"));
+    assert!(!matches_fuzzy("^nignx"));
+    assert!(matches_fuzzy("=nignx-unit"));
+    assert!(matches_fuzzy("enalbe$"));
+
+    let mut ranges = Vec::new();
+    let pattern = parse_pattern("^ser*=enable$");
+    assert!(
+      view
+        .find_matches(&[&pattern], Comparison::IgnoreCase, &mut ranges)
+        .unwrap()
+    );
+    assert_eq!(ranges, vec![0..3, 20..26]);
+  }
+
+  #[test]
+  fn test_string_view_matches_consume_bytes() {
+    let index = make_index_with_labels(vec!["foo".into(), "barbaz".into(), "foofoo".into()]);
+    let matches = |entry: &[u64], query| {
+      let entry = entry.iter().copied().map(LabelReference).collect::<Vec<_>>();
+      StringView::from((&index, entry.as_slice()))
+        .matches(&parse_pattern(query), Comparison::IgnoreCase)
+        .unwrap()
+    };
+
+    // query labels used to be searched from where the previous one started, so they could match the
+    // same bytes again
+    assert!(!matches(&[0], "foo*foo"));
+    assert!(matches(&[0, 0], "foo*foo"));
+    assert!(matches(&[2], "foo*foo"));
+    // and from the start of the label if the previous one was found in a later label
+    assert!(!matches(&[0, 1], "baz*bar"));
+    assert!(matches(&[0, 1], "bar*baz"));
+  }
+
+  #[test]
+  fn test_edit_distance() {
+    assert_eq!(edit_distance(b"nginx", b"nginx", 0), Some(0));
+    assert_eq!(edit_distance(b"nginx", b"NGINX", 0), Some(0));
+    assert_eq!(edit_distance(b"nginx", b"nignx", 2), Some(1));
+    assert_eq!(edit_distance(b"services", b"servics", 2), Some(1));
+    assert_eq!(edit_distance(b"nginx", b"ngimx-unit", 2), None);
+    assert_eq!(edit_distance(b"nginx", b"apache", 2), None);
+    assert_eq!(edit_distance(b"", b"ab", 2), Some(2));
+  }
+
+  #[test]
+  fn test_fuzzy_find() {
+    assert_eq!(fuzzy_find(b"services", b"servcies", 2), Some(0..8));
+    assert_eq!(fuzzy_find(b"nginx", b"nignx", 1), Some(0..5));
+    assert_eq!(fuzzy_find(b"nginx", b"nignx", 0), None);
+    assert_eq!(fuzzy_find(b"virtualHosts", b"hots", 1), Some(7..12));
+    assert_eq!(fuzzy_find(b"enableNixpkgs", b"NIXPKS", 1), Some(6..13));
+    assert_eq!(fuzzy_find(b"enable", b"xyz", 1), None);
+    assert_eq!(fuzzy_find(b"ab", b"abcd", 1), None);
+    // never splits a char
+    assert_eq!(fuzzy_find("näme".as_bytes(), b"nxme", 1), None);
+    assert_eq!(fuzzy_find("näme".as_bytes(), b"me", 0), Some(3..5));
   }
 
   #[test]
@@ -153,8 +715,8 @@ mod tests {
     let index = make_index_with_labels(vec!["foo".into()]);
     let entry = vec![LabelReference(0)];
     let view = StringView::from((&index, entry.as_slice()));
-    let pattern: Vec<Vec<&[u8]>> = vec![];
-    assert!(view.matches(&pattern).unwrap());
+    let pattern = parse_pattern("");
+    assert!(view.matches(&pattern, Comparison::IgnoreCase).unwrap());
   }
 
   #[test]
@@ -162,8 +724,8 @@ mod tests {
     let index = make_index_with_labels(vec![]);
     let entry = vec![];
     let view = StringView::from((&index, entry.as_slice()));
-    let pattern = vec![vec![b"foo".as_ref()]];
-    assert!(!view.matches(&pattern).unwrap());
+    let pattern = parse_pattern("foo");
+    assert!(!view.matches(&pattern, Comparison::IgnoreCase).unwrap());
   }
 
   #[test]
@@ -190,6 +752,54 @@ mod tests {
     );
   }
 
+  #[test]
+  fn test_ignore_case() {
+    assert_eq!(fold_case('Ä'), 'ä');
+    assert_eq!(fold_case('ẞ'), 'ß');
+    // lowers to two chars
+    assert_eq!(fold_case('İ'), 'İ');
+
+    assert_eq!(
+      ignore_case_find("fooÄrzte".as_bytes(), "ärz".as_bytes()),
+      Some(3..7)
+    );
+    assert_eq!(ignore_case_find(b"fooBar", b"bar"), Some(3..6));
+    // the Kelvin sign takes three bytes
+    assert_eq!(ignore_case_find("\u{212A}elvin".as_bytes(), b"kel"), Some(0..5));
+    assert_eq!(ignore_case_find("straße".as_bytes(), b"strasse"), None);
+
+    assert_eq!(ignore_case_prefix("ÖMER".as_bytes(), "öm".as_bytes()), Some(3));
+    assert_eq!(ignore_case_suffix("ÖMER".as_bytes(), "mer".as_bytes()), Some(3));
+    assert_eq!(ignore_case_suffix("ÖMER".as_bytes(), "ömer".as_bytes()), Some(5));
+    assert!(eq_ignore_case("ÖMER".as_bytes(), "ömer".as_bytes()));
+    assert!(!eq_ignore_case("ÖMER".as_bytes(), "öme".as_bytes()));
+  }
+
+  #[test]
+  fn test_acronym_find() {
+    let label = b"enableNixpkgsReleaseCheck";
+    assert_eq!(acronym_find(label, 0, b"eNRC"), Some(vec![0, 6, 13, 20]));
+    assert_eq!(acronym_find(label, 0, b"nrc"), Some(vec![6, 13, 20]));
+    assert_eq!(acronym_find(label, 7, b"nrc"), None);
+    // words have to follow each other
+    assert_eq!(acronym_find(label, 0, b"erc"), None);
+    assert_eq!(acronym_find(label, 0, b"e"), None);
+    assert_eq!(acronym_find(b"home-manager", 0, b"hM"), Some(vec![0, 5]));
+    assert_eq!(acronym_find(b"home-manager", 0, b"hm"), None);
+    assert_eq!(acronym_find(b"_foo--bar", 0, b"fB"), Some(vec![1, 6]));
+    assert_eq!(acronym_find(b"nginx", 0, b"n-x"), None);
+  }
+
+  #[test]
+  fn test_is_word_boundary() {
+    assert!(is_word_boundary(b"virtualHosts", 0));
+    assert!(is_word_boundary(b"virtualHosts", 7));
+    assert!(!is_word_boundary(b"virtualHosts", 6));
+    assert!(is_word_boundary(b"home-manager", 5));
+    assert!(!is_word_boundary(b"home-manager", 4));
+    assert!(!is_word_boundary(b"HOME", 2));
+  }
+
   #[test]
   fn test_eq_ignore_ascii_case() {
     for range in [b'a'..=b'z', b'A'..=b'Z'] {
@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+
+use crate::{
+  IxxError,
+  index::LabelReference,
+  query::{Alternative, Pattern, Query},
+  storage::Storage,
+  string_view::{StringView, edit_distance},
+};
+
+/// Proposes existing names close to `query`, e.g. after a search without results.
+///
+/// Every query label is compared to the whole label table. A name qualifies if every term which is not
+/// negated has an alternative with a close label for each of its query labels, in order, and is cut
+/// after the last of them, so `servics.ngimx` suggests `services.nginx` instead of every option below
+/// it. The closest alternative of every term counts and closer and shorter names come first.
+pub(crate) fn suggest(
+  storage: &impl Storage,
+  scope_id: Option<u32>,
+  query: &str,
+  max_results: usize,
+) -> Result<Vec<String>, IxxError> {
+  let query = Query::parse(query).map_err(|reason| IxxError::InvalidQuery {
+    query: query.to_string(),
+    reason,
+  })?;
+
+  // for every term, for every alternative, for every query label the distance of every label to it,
+  // if it is close enough
+  let terms = query
+    .terms
+    .iter()
+    .filter(|term| !term.negated)
+    .map(|term| {
+      term
+        .alternatives
+        .iter()
+        .filter_map(|alternative| match alternative {
+          Alternative::Pattern(pattern) => Some(distances(storage, pattern)),
+          Alternative::Scope(_) => None,
+        })
+        .filter(|parts| !parts.is_empty())
+        .collect::<Vec<_>>()
+    })
+    .filter(|alternatives| !alternatives.is_empty())
+    .collect::<Vec<_>>();
+
+  if terms.is_empty() || max_results == 0 {
+    return Ok(Vec::new());
+  }
+
+  let mut candidates = HashMap::<&[LabelReference], usize>::new();
+  for (entry_scope_id, labels) in storage.entries() {
+    if scope_id.is_some_and(|scope_id| scope_id != entry_scope_id) {
+      continue;
+    }
+    if let Some((distance, len)) = closest_terms(&terms, labels) {
+      candidates
+        .entry(&labels[..len])
+        .and_modify(|closest| *closest = (*closest).min(distance))
+        .or_insert(distance);
+    }
+  }
+
+  let mut candidates = candidates.into_iter().collect::<Vec<_>>();
+  candidates.sort_unstable_by_key(|(labels, distance)| (*distance, labels.len(), *labels));
+  candidates.truncate(max_results);
+
+  Ok(
+    candidates
+      .into_iter()
+      .map(|(labels, _)| StringView::from((storage, labels)).to_string())
+      .collect(),
+  )
+}
+
+/// Distance of every label to every query label of `pattern`, if it is close enough.
+fn distances(storage: &impl Storage, pattern: &Pattern) -> Vec<Vec<Option<usize>>> {
+  pattern
+    .parts()
+    .map(|part| {
+      let max_distance = part.label.len().div_ceil(3);
+      storage
+        .labels()
+        .map(|label| edit_distance(label, part.label.as_bytes(), max_distance))
+        .collect()
+    })
+    .collect()
+}
+
+/// Sums up the closest alternative of every term, see [`closest_prefix`], needing the labels of the
+/// name up to the last one any term needs.
+fn closest_terms(
+  terms: &[Vec<Vec<Vec<Option<usize>>>>],
+  labels: &[LabelReference],
+) -> Option<(usize, usize)> {
+  terms.iter().try_fold((0, 0), |(distance, len), alternatives| {
+    let (term_distance, term_len) = alternatives
+      .iter()
+      .filter_map(|distances| closest_prefix(distances, labels))
+      .min()?;
+    Some((distance + term_distance, len.max(term_len)))
+  })
+}
+
+/// Assigns the query labels, in order, to distinct labels of a name, minimizing the summed distance.
+///
+/// Returns that sum and how many labels of the name are needed for it.
+fn closest_prefix(distances: &[Vec<Option<usize>>], labels: &[LabelReference]) -> Option<(usize, usize)> {
+  // the best assignment of the first i query labels among the labels visited so far
+  let mut best = vec![None; distances.len() + 1];
+  best[0] = Some((0, 0));
+
+  for (idx, label) in labels.iter().enumerate() {
+    // backwards, so a label is not assigned to two query labels
+    for part in (0..distances.len()).rev() {
+      let Some((distance, _)) = best[part] else {
+        continue;
+      };
+      let Some(Some(label_distance)) = distances[part].get(label.0 as usize) else {
+        continue;
+      };
+
+      let candidate = (distance + label_distance, idx + 1);
+      if best[part + 1].is_none_or(|(best_distance, _)| candidate.0 < best_distance) {
+        best[part + 1] = Some(candidate);
+      }
+    }
+  }
+
+  best[distances.len()]
+}
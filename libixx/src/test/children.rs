@@ -0,0 +1,101 @@
+use crate::{Child, Index};
+
+fn children(index: &Index, scope_id: u32, prefix: &str) -> Vec<(String, usize, Option<usize>)> {
+  index
+    .children(scope_id, prefix)
+    .into_iter()
+    .map(|child| (child.label, child.entries, child.idx))
+    .collect()
+}
+
+#[test]
+fn test_children() {
+  let index = Index::build(
+    vec![
+      ("services.nginx.enable", 0),
+      ("services.nginx", 0),
+      ("services.nginx.virtualHosts.<name>.root", 0),
+      ("services.nginx.virtualHosts.<name>.locations", 0),
+      ("services.caddy.enable", 0),
+      ("services.nginx-unit.enable", 0),
+      (r#"services.nginx."example.com""#, 0),
+      ("programs.git.enable", 0),
+      ("services.nginx.package", 1),
+    ]
+    .as_slice(),
+  )
+  .unwrap();
+
+  assert_eq!(
+    children(&index, 0, ""),
+    vec![
+      ("programs".to_string(), 1, None),
+      ("services".to_string(), 7, None)
+    ]
+  );
+  assert_eq!(
+    children(&index, 0, "services"),
+    vec![
+      ("caddy".to_string(), 1, None),
+      ("nginx".to_string(), 5, Some(1)),
+      ("nginx-unit".to_string(), 1, None)
+    ]
+  );
+  assert_eq!(
+    children(&index, 0, "services.nginx"),
+    vec![
+      ("enable".to_string(), 1, Some(0)),
+      ("example.com".to_string(), 1, Some(6)),
+      ("virtualHosts".to_string(), 2, None)
+    ]
+  );
+  assert_eq!(
+    index.children(0, "services.nginx.virtualHosts.<name>"),
+    vec![
+      Child {
+        label: "locations".to_string(),
+        path: "services.nginx.virtualHosts.<name>.locations".to_string(),
+        entries: 1,
+        idx: Some(3)
+      },
+      Child {
+        label: "root".to_string(),
+        path: "services.nginx.virtualHosts.<name>.root".to_string(),
+        entries: 1,
+        idx: Some(2)
+      }
+    ]
+  );
+  assert_eq!(
+    children(&index, 1, "services.nginx"),
+    vec![("package".to_string(), 1, Some(8))]
+  );
+
+  // paths can be passed on as they are
+  let example = index
+    .children(0, "services.nginx")
+    .into_iter()
+    .find(|child| child.label == "example.com")
+    .unwrap();
+  assert_eq!(example.path, r#"services.nginx."example.com""#);
+  assert_eq!(index.get_idx_by_name(0, &example.path), example.idx);
+  assert_eq!(
+    index
+      .children(0, "services.nginx")
+      .into_iter()
+      .map(|child| child.path)
+      .collect::<Vec<_>>(),
+    vec![
+      "services.nginx.enable",
+      r#"services.nginx."example.com""#,
+      "services.nginx.virtualHosts"
+    ]
+  );
+  assert_eq!(children(&index, 0, "services.nginx.virtualHosts").len(), 1);
+
+  // entries have no children and unknown or malformed prefixes have none either
+  assert!(children(&index, 0, "services.nginx.enable").is_empty());
+  assert!(children(&index, 0, "services.apache").is_empty());
+  assert!(children(&index, 0, "services..nginx").is_empty());
+  assert!(children(&index, 2, "services").is_empty());
+}
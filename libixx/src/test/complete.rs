@@ -0,0 +1,44 @@
+use crate::Index;
+
+#[test]
+fn test_complete() {
+  let index = Index::build(
+    vec![
+      ("services.nginx.enable", 0),
+      ("services.nginx.package", 0),
+      ("services.nginx.user", 0),
+      ("services.nginx-unit.enable", 0),
+      ("services.nginx-unit.package", 0),
+      ("services.NGIRCd.enable", 0),
+      ("services.caddy.enable", 0),
+      (r#"services.nginx.virtualHosts."example.com".root"#, 0),
+      ("programs.nginx", 1),
+    ]
+    .as_slice(),
+  )
+  .unwrap();
+
+  // most frequent labels first, ignoring case
+  assert_eq!(
+    index.complete(0, "services.ngi", 10),
+    vec!["services.nginx", "services.nginx-unit", "services.NGIRCd"]
+  );
+  assert_eq!(
+    index.complete(0, "services.ngi", 2),
+    vec!["services.nginx", "services.nginx-unit"]
+  );
+  assert_eq!(index.complete(0, "serv", 10), vec!["services"]);
+  assert_eq!(index.complete(1, "", 10), vec!["programs"]);
+  // "user" and "virtualHosts" are equally frequent
+  let below = index.complete(0, "services.nginx.", 10);
+  assert_eq!(below.len(), 4);
+  assert_eq!(below[..2], ["services.nginx.enable", "services.nginx.package"]);
+  assert_eq!(
+    index.complete(0, r#"services.nginx.virtualHosts."exa"#, 10),
+    vec![r#"services.nginx.virtualHosts."example.com""#]
+  );
+
+  assert!(index.complete(0, "services.apache.", 10).is_empty());
+  assert!(index.complete(0, "services.nginx.enable.", 10).is_empty());
+  assert!(index.complete(0, "services.ngi", 0).is_empty());
+}
@@ -0,0 +1,96 @@
+use crate::{DiffEntry, Index, IndexDiff, Metadata, MovedEntry};
+
+#[test]
+fn test_diff() {
+  let old = Index::build(
+    vec![
+      ("services.nginx.enable", 0),
+      ("services.nginx.package", 0),
+      ("services.caddy.enable", 0),
+      ("programs.git.enable", 0),
+      ("programs.git.enable", 1),
+    ]
+    .as_slice(),
+  )
+  .unwrap();
+  let new = Index::build(
+    vec![
+      ("programs.git.enable", 1),
+      ("programs.git.package", 1),
+      ("services.caddy.enable", 1),
+      ("services.nginx.enable", 0),
+      ("services.nginx.package", 0),
+    ]
+    .as_slice(),
+  )
+  .unwrap();
+
+  assert_eq!(
+    old.diff(&new),
+    IndexDiff {
+      added: vec![DiffEntry {
+        name: "programs.git.package".to_string(),
+        scope_id: 1,
+        idx: 1,
+      }],
+      removed: vec![DiffEntry {
+        name: "programs.git.enable".to_string(),
+        scope_id: 0,
+        idx: 3,
+      }],
+      moved: vec![MovedEntry {
+        name: "services.caddy.enable".to_string(),
+        old_scope_id: 0,
+        new_scope_id: 1,
+        old_idx: 2,
+        new_idx: 2,
+      }],
+      unchanged: vec![(4, 0), (0, 3), (1, 4)],
+    }
+  );
+
+  let mut same = old.diff(&old);
+  assert!(same.added.is_empty() && same.removed.is_empty() && same.moved.is_empty());
+  same.unchanged.sort_unstable();
+  assert_eq!(
+    same.unchanged,
+    (0..old.size()).map(|idx| (idx, idx)).collect::<Vec<_>>()
+  );
+}
+
+#[test]
+fn test_diff_scope_names() {
+  let metadata = |scopes: &[&str]| Metadata {
+    scopes: scopes.iter().map(ToString::to_string).collect(),
+    ..Metadata::default()
+  };
+  let old = Index::build(&[("programs.git.enable", 0), ("programs.git.enable", 1)])
+    .unwrap()
+    .with_metadata(metadata(&["NixOS", "Home Manager"]));
+  // scopes were reordered and one was added
+  let new = Index::build(&[
+    ("programs.git.enable", 0),
+    ("programs.git.enable", 1),
+    ("programs.git.enable", 2),
+  ])
+  .unwrap()
+  .with_metadata(metadata(&["nix-darwin", "Home Manager", "NixOS"]));
+
+  let diff = old.diff(&new);
+  assert_eq!(
+    diff.added,
+    vec![DiffEntry {
+      name: "programs.git.enable".to_string(),
+      scope_id: 0,
+      idx: 0,
+    }]
+  );
+  assert!(diff.removed.is_empty() && diff.moved.is_empty());
+  assert_eq!(diff.unchanged, vec![(0, 2), (1, 1)]);
+
+  // without scope names on either side, scope ids are compared
+  let diff = old.diff(&new.clone().with_metadata(Metadata::default()));
+  assert_eq!(diff.added.len(), 1);
+  assert_eq!(diff.added[0].scope_id, 2);
+  assert_eq!(diff.unchanged, vec![(0, 0), (1, 1)]);
+}
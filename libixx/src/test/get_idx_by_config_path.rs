@@ -0,0 +1,116 @@
+use crate::{Index, IndexView};
+
+fn index() -> Index {
+  Index::build(
+    vec![
+      ("services.nginx.enable", 0),
+      ("services.nginx.virtualHosts", 0),
+      ("services.nginx.virtualHosts.<name>.root", 0),
+      ("services.nginx.virtualHosts.<name>.locations.<name>.proxyPass", 0),
+      ("services.nginx.virtualHosts.default.root", 0),
+      ("services.foo.settings", 0),
+      ("users.users.<name>.shell", 0),
+      ("boot.loader.*.enable", 0),
+      ("services.nginx.virtualHosts.<name>.root", 1),
+    ]
+    .as_slice(),
+  )
+  .unwrap()
+}
+
+#[test]
+fn test_get_idx_by_config_path() {
+  let index = index();
+
+  assert_eq!(
+    index.get_idx_by_config_path(0, "services.nginx.virtualHosts.example.root"),
+    Some(2)
+  );
+  assert_eq!(
+    index.get_idx_by_config_path(0, r#"services.nginx.virtualHosts."example.com".root"#),
+    Some(2)
+  );
+  assert_eq!(
+    index.get_idx_by_config_path(
+      0,
+      r#"services.nginx.virtualHosts."example.com".locations."/".proxyPass"#
+    ),
+    Some(3)
+  );
+  assert_eq!(
+    index.get_idx_by_config_path(0, "users.users.alice.shell"),
+    Some(6)
+  );
+  assert_eq!(
+    index.get_idx_by_config_path(0, "boot.loader.grub.enable"),
+    Some(7)
+  );
+  assert_eq!(
+    index.get_idx_by_config_path(1, "services.nginx.virtualHosts.example.root"),
+    Some(8)
+  );
+}
+
+#[test]
+fn test_get_idx_by_config_path_prefers_exact_labels() {
+  let index = index();
+
+  assert_eq!(
+    index.get_idx_by_config_path(0, "services.nginx.virtualHosts.default.root"),
+    Some(4)
+  );
+  assert_eq!(
+    index.get_idx_by_config_path(0, "services.nginx.virtualHosts.<name>.root"),
+    Some(2)
+  );
+  assert_eq!(index.get_idx_by_config_path(0, "services.nginx.enable"), Some(0));
+}
+
+#[test]
+fn test_get_idx_by_config_path_unknown() {
+  let index = index();
+
+  // only the whole path is resolved, not the option declaring a prefix of it
+  assert_eq!(
+    index.get_idx_by_config_path(0, "services.nginx.enable.typo"),
+    None
+  );
+  assert_eq!(
+    index.get_idx_by_config_path(0, "services.nginx.doesNotExist"),
+    None
+  );
+  assert_eq!(
+    index.get_idx_by_config_path(0, "services.foo.settings.server.port"),
+    None
+  );
+  assert_eq!(
+    index.get_idx_by_config_path(0, "services.nginx.virtualHosts.example.extraConfig"),
+    None
+  );
+  assert_eq!(index.get_idx_by_config_path(0, "services.bar.enable"), None);
+  assert_eq!(index.get_idx_by_config_path(2, "services.nginx.enable"), None);
+  assert_eq!(index.get_idx_by_config_path(0, "services..enable"), None);
+}
+
+#[test]
+fn test_get_idx_by_config_path_view() {
+  let index = index();
+  let mut buf = std::io::Cursor::new(Vec::new());
+  index.write_into(&mut buf).unwrap();
+  let view = IndexView::read(buf.get_ref()).unwrap();
+
+  for path in [
+    "services.nginx.virtualHosts.example.root",
+    "services.nginx.virtualHosts.example.locations./.proxyPass",
+    "services.foo.settings.server.port",
+    "services.foo.settings",
+    "users.users.alice.shell",
+    "services.bar.enable",
+  ] {
+    assert_eq!(
+      view.get_idx_by_config_path(0, path),
+      index.get_idx_by_config_path(0, path),
+      "{path}"
+    );
+  }
+}
@@ -2,7 +2,7 @@ use crate::Index;
 
 #[test]
 fn test_get_idx_by_name_one_level() {
-  let index = Index::build(vec![("a", 0), ("b", 0), ("c", 0)].as_slice());
+  let index = Index::build(vec![("a", 0), ("b", 0), ("c", 0)].as_slice()).unwrap();
 
   assert_eq!(
     index.get_idx_by_name(0, "a"),
@@ -33,7 +33,8 @@ fn test_get_idx_by_name_complex() {
       ("home.enableDebugInfo", 1),
     ]
     .as_slice(),
-  );
+  )
+  .unwrap();
 
   assert_eq!(index.get_idx_by_name(0, "home.enableDebugInfo"), Some(0));
 
@@ -53,3 +54,86 @@ fn test_get_idx_by_name_complex() {
     "Should find 'nixosTests.allDrivers.pretalx' in scope 0"
   );
 }
+
+#[test]
+fn test_get_idx_by_name_every_entry() {
+  let names = (0..500)
+    .rev()
+    .map(|idx| (format!("pkgs{}.package{idx}", idx % 7), idx % 3))
+    .collect::<Vec<_>>();
+  let index = Index::build(
+    names
+      .iter()
+      .map(|(name, scope_id)| (name.as_str(), *scope_id))
+      .collect::<Vec<_>>()
+      .as_slice(),
+  )
+  .unwrap();
+
+  for (idx, (name, scope_id)) in names.iter().enumerate() {
+    assert_eq!(index.get_idx_by_name(*scope_id, name), Some(idx));
+    assert_eq!(index.get_idx_by_name((*scope_id + 1) % 3, name), None);
+  }
+
+  assert_eq!(index.get_idx_by_name(0, "pkgs0"), None);
+  assert_eq!(index.get_idx_by_name(0, "pkgs0.package0.extra"), None);
+  assert_eq!(index.get_idx_by_name(0, "package0.pkgs0"), None);
+}
+
+#[test]
+fn test_get_idx_by_name_duplicates() {
+  let index = Index::build(vec![("b", 0), ("a", 0), ("b", 0), ("b", 1)].as_slice()).unwrap();
+
+  assert_eq!(index.get_idx_by_name(0, "b"), Some(0));
+  assert_eq!(index.get_idx_by_name(1, "b"), Some(3));
+}
+
+#[test]
+fn test_get_idx_by_name_quoted() {
+  let index = Index::build(
+    vec![
+      (r#"services.nginx.virtualHosts."example.com".root"#, 0),
+      ("services.nginx.virtualHosts.example.root", 0),
+      (r#"services.nginx.virtualHosts."with \"quotes\"".root"#, 0),
+      ("services.nginx.virtualHosts.<name>.root", 0),
+    ]
+    .as_slice(),
+  )
+  .unwrap();
+
+  assert_eq!(
+    index.get_idx_by_name(0, r#"services.nginx.virtualHosts."example.com".root"#),
+    Some(0)
+  );
+  assert_eq!(
+    index.get_idx_by_name(0, "services.nginx.virtualHosts.example.com.root"),
+    None
+  );
+  // quoting labels which do not need it is allowed
+  assert_eq!(
+    index.get_idx_by_name(0, r#"services.nginx."virtualHosts"."example".root"#),
+    Some(1)
+  );
+  assert_eq!(
+    index.get_idx_by_name(0, r#"services.nginx.virtualHosts."with \"quotes\"".root"#),
+    Some(2)
+  );
+  assert_eq!(
+    index.get_idx_by_name(0, "services.nginx.virtualHosts.<name>.root"),
+    Some(3)
+  );
+  assert_eq!(
+    index.get_idx_by_name(0, r#"services.nginx.virtualHosts."example.com.root"#),
+    None
+  );
+
+  // names are displayed the way they were built
+  for (idx, hit) in index.search(None, "root", 10).unwrap().into_iter().enumerate() {
+    assert_eq!(
+      index.get_idx_by_name(0, &hit.name),
+      Some(hit.idx),
+      "{idx}: {}",
+      hit.name
+    );
+  }
+}
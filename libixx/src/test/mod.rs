@@ -1,2 +1,10 @@
+mod children;
+mod complete;
+mod diff;
+mod get_idx_by_config_path;
 mod get_idx_by_name;
+mod name_of;
 mod search;
+#[cfg(feature = "regex")]
+mod search_regex;
+mod suggest;
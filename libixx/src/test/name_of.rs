@@ -0,0 +1,29 @@
+use crate::Index;
+
+#[test]
+fn test_name_of() {
+  let names = [
+    ("services.nginx.enable", 0),
+    (r#"services.nginx.virtualHosts."example.com".root"#, 0),
+    ("home.enableDebugInfo", 1),
+    ("services.nginx.enable", 2),
+  ];
+  let index = Index::build(names.as_slice()).unwrap();
+
+  for (idx, (name, scope_id)) in names.iter().enumerate() {
+    assert_eq!(index.name_of(idx).as_deref(), Some(*name));
+    assert_eq!(index.scope_of(idx), Some(*scope_id));
+    assert_eq!(index.get_idx_by_name(*scope_id, name), Some(idx));
+  }
+  assert_eq!(index.name_of(names.len()), None);
+  assert_eq!(index.scope_of(names.len()), None);
+
+  assert_eq!(
+    index.entries().collect::<Vec<_>>(),
+    names
+      .iter()
+      .enumerate()
+      .map(|(idx, (name, scope_id))| (idx, *scope_id, name.to_string()))
+      .collect::<Vec<_>>()
+  );
+}
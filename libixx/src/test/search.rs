@@ -1,4 +1,18 @@
-use crate::Index;
+use crate::{Index, IxxError, Metadata, SearchHit, SearchOptions};
+
+fn search(
+  index: &Index,
+  scope_id: Option<u32>,
+  query: &str,
+  max_results: usize,
+) -> Vec<(usize, u32, String)> {
+  index
+    .search(scope_id, query, max_results)
+    .unwrap()
+    .into_iter()
+    .map(|hit| (hit.idx, hit.scope_id, hit.name))
+    .collect()
+}
 
 #[test]
 fn test() {
@@ -19,10 +33,11 @@ fn test() {
       ("home.enableDebugInfo", 1),
     ]
     .as_slice(),
-  );
+  )
+  .unwrap();
 
   assert_eq!(
-    index.search(None, "ho*auto", 10).unwrap(),
+    search(&index, None, "ho*auto", 10),
     vec![
       (7, 0, "services.home-manager.autoUpgrade.enable".to_string()),
       (8, 0, "services.home-manager.autoUpgrade.frequency".to_string())
@@ -30,41 +45,38 @@ fn test() {
   );
 
   assert_eq!(
-    index.search(None, "ho*auto*ena", 10).unwrap(),
+    search(&index, None, "ho*auto*ena", 10),
     vec![(7, 0, "services.home-manager.autoUpgrade.enable".to_string())]
   );
 
   assert_eq!(
-    index.search(None, "ho*en*Nix", 10).unwrap(),
+    search(&index, None, "ho*en*Nix", 10),
     vec![(1, 0, "home.enableNixpkgsReleaseCheck".to_string())]
   );
 
   assert_eq!(
-    index.search(None, "ho*en*Nix*Rel*Che", 10).unwrap(),
+    search(&index, None, "ho*en*Nix*Rel*Che", 10),
     vec![(1, 0, "home.enableNixpkgsReleaseCheck".to_string())]
   );
 
   assert_eq!(
-    index.search(None, "enablenixpkgsreleasecheck", 10).unwrap(),
+    search(&index, None, "enablenixpkgsreleasecheck", 10),
     vec![(1, 0, "home.enableNixpkgsReleaseCheck".to_string())]
   );
 
   // TEST scopes
   assert_eq!(
-    index.search(Some(0), "enablenixpkgsreleasecheck", 10).unwrap(),
+    search(&index, Some(0), "enablenixpkgsreleasecheck", 10),
     vec![(1, 0, "home.enableNixpkgsReleaseCheck".to_string())]
   );
-  assert_eq!(
-    index.search(Some(1), "enablenixpkgsreleasecheck", 10).unwrap(),
-    vec![]
-  );
+  assert_eq!(search(&index, Some(1), "enablenixpkgsreleasecheck", 10), vec![]);
 
   // query with no matches
-  assert_eq!(index.search(None, "nonexistent.option", 10).unwrap(), vec![]);
+  assert_eq!(search(&index, None, "nonexistent.option", 10), vec![]);
 
   // TEST options with same name in different scopes
   assert_eq!(
-    index.search(None, "ho*debug", 10).unwrap(),
+    search(&index, None, "ho*debug", 10),
     vec![
       (0, 0, "home.enableDebugInfo".to_string()),
       (12, 1, "home.enableDebugInfo".to_string())
@@ -83,33 +95,524 @@ fn test_exact_search() {
       ("programs.vim.enable", 0),
     ]
     .as_slice(),
-  );
+  )
+  .unwrap();
 
   assert_eq!(
-    index.search(Some(0), "programs.neovim", 10).unwrap(),
+    search(&index, Some(0), "programs.neovim", 10),
     vec![(0, 0, "programs.neovim.enable".to_string())]
   );
   assert_eq!(
-    index.search(Some(0), "programs.neovim.enable", 10).unwrap(),
+    search(&index, Some(0), "programs.neovim.enable", 10),
     vec![(0, 0, "programs.neovim.enable".to_string())]
   );
 
   assert_eq!(
-    index.search(Some(0), "programs.nixvim", 10).unwrap(),
+    search(&index, Some(0), "programs.nixvim", 10),
     vec![(1, 0, "programs.nixvim.enable".to_string())]
   );
   assert_eq!(
-    index.search(Some(0), "programs.nixvim.enable", 10).unwrap(),
+    search(&index, Some(0), "programs.nixvim.enable", 10),
     vec![(1, 0, "programs.nixvim.enable".to_string())]
   );
 
   // regression test that no out of bounds happen
+  assert_eq!(search(&index, Some(0), "programs.vim.enable.extra", 10), vec![]);
+  assert_eq!(search(&index, Some(0), "programs*vim.enable.extra", 10), vec![]);
+}
+
+#[test]
+fn test_ranking_does_not_depend_on_position() {
+  let mut names = (0..600)
+    .map(|idx| format!("aaa.package{idx:03}.hello-extra"))
+    .collect::<Vec<_>>();
+  names.push("hello".to_string());
+  names.push("hello-wayland".to_string());
+
+  let entries = names.iter().map(|name| (name.as_str(), 0)).collect::<Vec<_>>();
+  let index = Index::build(entries.as_slice()).unwrap();
+
+  // the exact hit sits behind 600 other matches in index order
+  let results = search(&index, None, "hello", 10);
+  assert_eq!(results.len(), 10);
+  assert_eq!(results[0], (600, 0, "hello".to_string()));
+  assert_eq!(results[1], (601, 0, "hello-wayland".to_string()));
+
+  // results with the same distance keep their index order
+  assert_eq!(results[2].0, 0);
+  assert!(results.windows(2).skip(2).all(|pair| pair[0].0 < pair[1].0));
+
+  assert_eq!(search(&index, None, "hello", 0), vec![]);
+  assert_eq!(search(&index, None, "hello", 1000).len(), 602);
+}
+
+#[test]
+fn test_relevance() {
+  let index = Index::build(
+    vec![
+      ("nginx", 0),
+      ("nginxModules.brotli", 0),
+      ("services.nginx.enable", 0),
+      ("services.nginx.virtualHosts.<name>.locations.<name>.proxyPass", 0),
+      ("services.nginx-unit.enable", 0),
+      ("services.tengine-nginx.enable", 0),
+      ("services.xnginxy.enable", 0),
+    ]
+    .as_slice(),
+  )
+  .unwrap();
+
   assert_eq!(
-      index.search(Some(0), "programs.vim.enable.extra", 10).unwrap(),
-      vec![]
+    search(&index, None, "nginx", 10)
+      .into_iter()
+      .map(|(_, _, name)| name)
+      .collect::<Vec<_>>(),
+    vec![
+      "nginx",
+      "services.nginx.enable",
+      "services.nginx.virtualHosts.<name>.locations.<name>.proxyPass",
+      "nginxModules.brotli",
+      "services.nginx-unit.enable",
+      "services.tengine-nginx.enable",
+      "services.xnginxy.enable",
+    ]
   );
+
+  let hits = index.search(None, "nginx", 10).unwrap();
+  assert!(hits.windows(2).all(|pair| pair[0].score >= pair[1].score));
+  assert!(hits[0].score.exact_name);
+  assert_eq!(hits[1].score.exact_labels, 1);
+}
+
+#[test]
+fn test_match_ranges() {
+  let index = Index::build(
+    vec![
+      ("home.enableNixpkgsReleaseCheck", 0),
+      ("services.home-manager.autoUpgrade.enable", 0),
+    ]
+    .as_slice(),
+  )
+  .unwrap();
+
+  let hits = index.search(None, "ho*en*Nix", 10).unwrap();
+  assert_eq!(hits.len(), 1);
+  assert_eq!(hits[0].matches, vec![0..2, 5..7, 11..14]);
+  assert_eq!(&hits[0].name[11..14], "Nix");
+
+  let hits = index.search(None, "auto*ena", 10).unwrap();
+  assert_eq!(hits.len(), 1);
   assert_eq!(
-      index.search(Some(0), "programs*vim.enable.extra", 10).unwrap(),
-      vec![]
+    hits[0]
+      .matches
+      .iter()
+      .map(|range| &hits[0].name[range.clone()])
+      .collect::<Vec<_>>(),
+    vec!["auto", "ena"]
   );
 }
+
+#[test]
+fn test_quoted_names() {
+  let index = Index::build(
+    vec![
+      (r#"services.nginx.virtualHosts."example.com".root"#, 0),
+      ("services.nginx.virtualHosts.example.root", 0),
+      (r#"users.users."$user".shell"#, 0),
+    ]
+    .as_slice(),
+  )
+  .unwrap();
+
+  assert_eq!(
+    search(&index, None, r#"hosts."example.com""#, 10),
+    vec![(
+      0,
+      0,
+      r#"services.nginx.virtualHosts."example.com".root"#.to_string()
+    )]
+  );
+  // an unterminated quote while typing
+  assert_eq!(search(&index, None, r#"hosts."example.c"#, 10).len(), 1);
+
+  let hits = index.search(None, "com.root", 10).unwrap();
+  assert_eq!(hits.len(), 1);
+  assert_eq!(
+    hits[0]
+      .matches
+      .iter()
+      .map(|range| &hits[0].name[range.clone()])
+      .collect::<Vec<_>>(),
+    vec!["com", "root"]
+  );
+
+  let hits = index.search(None, "user*shell", 10).unwrap();
+  assert_eq!(hits[0].name, r#"users.users."\$user".shell"#);
+  assert_eq!(
+    hits[0]
+      .matches
+      .iter()
+      .map(|range| &hits[0].name[range.clone()])
+      .collect::<Vec<_>>(),
+    vec!["user", "shell"]
+  );
+}
+
+#[test]
+fn test_fuzzy() {
+  let index = Index::build(
+    vec![
+      ("services.nginx.enable", 0),
+      ("services.nginx.virtualHosts", 0),
+      ("services.tengine.enable", 0),
+      ("servicesHelper.nignx", 0),
+      ("nginx", 0),
+    ]
+    .as_slice(),
+  )
+  .unwrap();
+  let fuzzy = SearchOptions {
+    fuzzy: true,
+    ..SearchOptions::default()
+  };
+  let names = |hits: Vec<SearchHit>| hits.into_iter().map(|hit| hit.name).collect::<Vec<_>>();
+
+  assert!(index.search(None, "servcies.nignx", 10).unwrap().is_empty());
+  assert_eq!(
+    names(index.search_with(None, "servcies.nignx", 10, &fuzzy).unwrap()),
+    vec![
+      "servicesHelper.nignx",
+      "services.nginx.enable",
+      "services.nginx.virtualHosts"
+    ]
+  );
+
+  // names matching as typed rank above everything found with typos
+  let hits = index.search_with(None, "nignx", 10, &fuzzy).unwrap();
+  assert_eq!(
+    names(hits.clone()),
+    vec![
+      "servicesHelper.nignx",
+      "nginx",
+      "services.nginx.enable",
+      "services.nginx.virtualHosts"
+    ]
+  );
+  assert!(hits[0].score.verbatim);
+  assert!(!hits[1].score.verbatim);
+  assert!(hits[0].score.value() > hits[1].score.value());
+  assert_eq!(hits[2].matches, vec![9..14]);
+
+  // short query labels have to match exactly
+  assert!(index.search_with(None, "xn", 10, &fuzzy).unwrap().is_empty());
+}
+
+#[test]
+fn test_multiple_terms() {
+  let index = Index::build(
+    vec![
+      ("services.nginx.enable", 0),
+      ("services.nginx.package", 0),
+      ("services.tengine.enable", 0),
+      ("programs.nginx-language-server.enable", 0),
+    ]
+    .as_slice(),
+  )
+  .unwrap();
+  let names = |query| {
+    index
+      .search(None, query, 10)
+      .unwrap()
+      .into_iter()
+      .map(|hit| hit.name)
+      .collect::<Vec<_>>()
+  };
+
+  // every term has to match, in any order
+  assert_eq!(
+    names("nginx enable"),
+    vec!["services.nginx.enable", "programs.nginx-language-server.enable"]
+  );
+  assert_eq!(names("enable nginx"), names("nginx enable"));
+  assert_eq!(names("enable  nginx\tservices"), vec!["services.nginx.enable"]);
+  assert!(names("nginx nonexistent").is_empty());
+
+  // segments inside of a term keep their order
+  assert_eq!(names("nginx*enable").len(), 2);
+  assert!(names("enable*nginx").is_empty());
+
+  // whitespace inside of quotes is not a separator
+  assert!(names(r#""nginx enable""#).is_empty());
+
+  // ranges of overlapping terms are merged
+  let hits = index.search(None, "nginx gin services", 10).unwrap();
+  assert_eq!(hits.len(), 2);
+  assert_eq!(hits[0].matches, vec![0..8, 9..14]);
+}
+
+#[test]
+fn test_query_operators() {
+  let index = Index::build(
+    vec![
+      ("services.nginx.enable", 0),
+      ("services.nginx.package", 0),
+      ("services.nginx-unit.enable", 0),
+      ("services.httpd.enable", 0),
+      ("programs.nginx.enable", 1),
+      ("nginx", 1),
+    ]
+    .as_slice(),
+  )
+  .unwrap()
+  .with_metadata(Metadata {
+    scopes: vec!["NixOS".to_string(), "Home Manager".to_string()],
+    ..Metadata::default()
+  });
+  let names = |query| {
+    let mut names = index
+      .search(None, query, 10)
+      .unwrap()
+      .into_iter()
+      .map(|hit| hit.name)
+      .collect::<Vec<_>>();
+    names.sort();
+    names
+  };
+
+  assert_eq!(names("^nginx"), vec!["nginx"]);
+  assert_eq!(
+    names("^services*enable$ nginx"),
+    vec!["services.nginx-unit.enable", "services.nginx.enable"]
+  );
+  assert_eq!(
+    names("=nginx enable"),
+    vec!["programs.nginx.enable", "services.nginx.enable"]
+  );
+  assert_eq!(
+    names("nginx -unit -package"),
+    vec!["nginx", "programs.nginx.enable", "services.nginx.enable"]
+  );
+  assert_eq!(
+    names("=nginx|httpd enable -programs"),
+    vec!["services.httpd.enable", "services.nginx.enable"]
+  );
+
+  // scopes by name, ignoring case, and by id
+  assert_eq!(
+    names(r#"nginx scope:"home manager""#),
+    vec!["nginx", "programs.nginx.enable"]
+  );
+  assert_eq!(
+    names("enable scope:1|httpd"),
+    vec!["programs.nginx.enable", "services.httpd.enable"]
+  );
+  assert_eq!(names("nginx -scope:NixOS"), names("nginx scope:1"));
+
+  for query in ["nginx|", "scope:unknown", "-", "nginx$enable"] {
+    assert!(matches!(
+      index.search(None, query, 10),
+      Err(IxxError::InvalidQuery { .. })
+    ));
+  }
+}
+
+#[test]
+fn test_word_boundaries() {
+  let index = Index::build(
+    vec![
+      ("home.enableNixpkgsReleaseCheck", 0),
+      ("home.enableDebugInfo", 0),
+      ("programs.home-manager.enable", 0),
+      ("services.xserver.displayManager.lightdm.enable", 0),
+      ("programs.ohmyzsh.enable", 0),
+    ]
+    .as_slice(),
+  )
+  .unwrap();
+  let names = |query| {
+    index
+      .search(None, query, 10)
+      .unwrap()
+      .into_iter()
+      .map(|hit| hit.name)
+      .collect::<Vec<_>>()
+  };
+
+  assert_eq!(names("eNRC"), vec!["home.enableNixpkgsReleaseCheck"]);
+  assert_eq!(names("home.nrc"), vec!["home.enableNixpkgsReleaseCheck"]);
+  assert_eq!(
+    names("enable nixpkgs release"),
+    vec!["home.enableNixpkgsReleaseCheck"]
+  );
+
+  // acronyms of two letters need mixed case, and rank below words starting with the query
+  assert_eq!(names("hm"), vec!["programs.ohmyzsh.enable"]);
+  assert_eq!(
+    names("hM"),
+    vec!["programs.home-manager.enable", "programs.ohmyzsh.enable"]
+  );
+  assert_eq!(
+    names("man"),
+    vec![
+      "programs.home-manager.enable",
+      "services.xserver.displayManager.lightdm.enable"
+    ]
+  );
+  assert_eq!(names("info"), vec!["home.enableDebugInfo"]);
+
+  let hits = index.search(None, "eDI", 10).unwrap();
+  assert_eq!(hits.len(), 1);
+  assert_eq!(hits[0].matches, vec![5..6, 11..12, 16..17]);
+}
+
+#[test]
+fn test_case() {
+  let index = Index::build(
+    vec![
+      ("services.Ärzte.enable", 0),
+      ("users.users.ÖMER.home", 0),
+      ("programs.straße.enable", 0),
+      ("home.enableDebugInfo", 0),
+    ]
+    .as_slice(),
+  )
+  .unwrap();
+  let case_sensitive = SearchOptions {
+    case_sensitive: true,
+    ..SearchOptions::default()
+  };
+  let names = |query: &str, options: &SearchOptions| {
+    index
+      .search_with(None, query, 10, options)
+      .unwrap()
+      .into_iter()
+      .map(|hit| hit.name)
+      .collect::<Vec<_>>()
+  };
+
+  // non-ASCII letters only differing in case
+  assert_eq!(
+    names("ärzte", &SearchOptions::default()),
+    vec![r#"services."Ärzte".enable"#]
+  );
+  assert_eq!(
+    names("users.ömer$", &SearchOptions::default()),
+    Vec::<String>::new()
+  );
+  assert_eq!(
+    names("users.=ömer", &SearchOptions::default()),
+    vec![r#"users.users."ÖMER".home"#]
+  );
+  assert_eq!(names("STRASSE", &SearchOptions::default()), Vec::<String>::new());
+  assert_eq!(
+    names("STRAßE", &SearchOptions::default()),
+    vec![r#"programs."straße".enable"#]
+  );
+
+  let hits = index
+    .search_with(None, "ärz", 10, &SearchOptions::default())
+    .unwrap();
+  assert_eq!(hits[0].matches, vec![10..14]);
+  assert_eq!(&hits[0].name[10..14], "Ärz");
+
+  // exact case
+  assert_eq!(
+    names("Ärzte", &case_sensitive),
+    vec![r#"services."Ärzte".enable"#]
+  );
+  assert!(names("ärzte", &case_sensitive).is_empty());
+  assert_eq!(names("DebugInfo", &case_sensitive), vec!["home.enableDebugInfo"]);
+  assert!(names("debuginfo", &case_sensitive).is_empty());
+  assert!(names("eDI", &case_sensitive).is_empty());
+  assert_eq!(
+    names("eDI", &SearchOptions::default()),
+    vec!["home.enableDebugInfo"]
+  );
+}
+
+#[test]
+fn test_pages() {
+  let index = Index::build(
+    vec![
+      ("services.nginx.enable", 0),
+      ("services.nginx.package", 0),
+      ("services.nginx.virtualHosts", 0),
+      ("services.nginx.user", 0),
+      ("services.nginx.group", 0),
+      ("services.caddy.enable", 0),
+      ("services.nginx.enable", 1),
+    ]
+    .as_slice(),
+  )
+  .unwrap();
+  let options = SearchOptions::default();
+
+  let all = index.search(None, "nginx", 10).unwrap();
+  assert_eq!(all.len(), 6);
+
+  let mut paged = Vec::new();
+  for offset in (0..8).step_by(2) {
+    let page = index.search_page(None, "nginx", offset, 2, &options).unwrap();
+    assert_eq!(page.total, 6);
+    assert!(page.hits.len() <= 2);
+    paged.extend(page.hits);
+  }
+  assert_eq!(paged, all);
+
+  let page = index.search_page(Some(0), "nginx", 0, 0, &options).unwrap();
+  assert!(page.hits.is_empty());
+  assert_eq!(page.total, 5);
+
+  let page = index.search_page(None, "nginx -user", 4, 10, &options).unwrap();
+  assert_eq!(page.total, 5);
+  assert_eq!(page.hits.len(), 1);
+
+  let page = index.search_page(None, "nonexistent", 0, 10, &options).unwrap();
+  assert_eq!(page.total, 0);
+}
+
+#[test]
+fn test_iter() {
+  let index = Index::build(
+    vec![
+      ("services.nginx.enable", 0),
+      ("services.caddy.enable", 0),
+      ("services.nginx.package", 0),
+      ("programs.nginx-unit.enable", 0),
+      ("services.nginx.enable", 1),
+    ]
+    .as_slice(),
+  )
+  .unwrap();
+  let options = SearchOptions::default();
+
+  // index order, not ranked
+  let hits = index
+    .search_iter(None, "nginx", &options)
+    .unwrap()
+    .collect::<Result<Vec<_>, _>>()
+    .unwrap();
+  assert_eq!(
+    hits.iter().map(|hit| hit.idx).collect::<Vec<_>>(),
+    vec![0, 2, 3, 4]
+  );
+
+  // the same hits as ranked searches
+  let mut ranked = index.search(None, "nginx", 10).unwrap();
+  ranked.sort_by_key(|hit| hit.idx);
+  assert_eq!(hits, ranked);
+
+  let first = index
+    .search_iter(Some(0), "enable", &options)
+    .unwrap()
+    .map(Result::unwrap)
+    .find(|hit| !hit.name.contains("nginx"))
+    .unwrap();
+  assert_eq!(first.name, "services.caddy.enable");
+  assert_eq!(first.matches, vec![15..21]);
+
+  assert!(matches!(
+    index.search_iter(None, "nginx|", &options),
+    Err(IxxError::InvalidQuery { .. })
+  ));
+}
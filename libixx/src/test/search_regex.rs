@@ -0,0 +1,82 @@
+use crate::{Index, IxxError};
+
+fn search_regex(index: &Index, scope_id: Option<u32>, pattern: &str) -> Vec<String> {
+  index
+    .search_regex(scope_id, pattern, 10)
+    .unwrap()
+    .into_iter()
+    .map(|hit| hit.name)
+    .collect()
+}
+
+#[test]
+fn test_regex() {
+  let index = Index::build(
+    vec![
+      ("python3Packages.requests", 0),
+      ("python3Packages.black-bin", 0),
+      ("python311Packages.ruff-bin", 0),
+      ("python3.pkgs.ruff-bin", 0),
+      ("nodePackages.prettier-bin", 0),
+      (r#"services.nginx.virtualHosts."example.com".root"#, 0),
+      ("python3Packages.mypy-bin", 1),
+    ]
+    .as_slice(),
+  )
+  .unwrap();
+
+  // shallow names and lower scopes first
+  assert_eq!(
+    search_regex(&index, None, r"^python3.*Packages\..*-bin$"),
+    vec![
+      "python3Packages.black-bin",
+      "python311Packages.ruff-bin",
+      "python3Packages.mypy-bin"
+    ]
+  );
+  assert_eq!(
+    search_regex(&index, Some(1), r"^python3.*Packages\..*-bin$"),
+    vec!["python3Packages.mypy-bin"]
+  );
+  assert_eq!(
+    search_regex(&index, None, r"(?i)^PYTHON3PACKAGES\.(requests|mypy)"),
+    vec!["python3Packages.requests", "python3Packages.mypy-bin"]
+  );
+  assert!(search_regex(&index, None, r"^python3packages").is_empty());
+
+  // names are matched as displayed, with quoted labels
+  assert_eq!(
+    search_regex(&index, None, r#"Hosts\."example\.com"\.root$"#),
+    vec![r#"services.nginx.virtualHosts."example.com".root"#]
+  );
+
+  let hits = index.search_regex(None, r"-bin|^python3\.", 10).unwrap();
+  assert_eq!(hits[4].name, "python3.pkgs.ruff-bin");
+  assert_eq!(hits[4].matches, vec![0..8, 17..21]);
+
+  assert!(matches!(
+    index.search_regex(None, "(unclosed", 10),
+    Err(IxxError::InvalidRegex(_))
+  ));
+}
+
+#[test]
+fn test_regex_pages() {
+  let index = Index::build(
+    vec![
+      ("python3Packages.black-bin", 0),
+      ("python3Packages.ruff-bin", 0),
+      ("python3Packages.mypy-bin", 0),
+      ("python3Packages.requests", 0),
+    ]
+    .as_slice(),
+  )
+  .unwrap();
+
+  let page = index.search_regex_page(None, "-bin$", 1, 1).unwrap();
+  assert_eq!(page.total, 3);
+  assert_eq!(
+    page.hits.into_iter().map(|hit| hit.name).collect::<Vec<_>>(),
+    vec!["python3Packages.ruff-bin"]
+  );
+}
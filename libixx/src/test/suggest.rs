@@ -0,0 +1,84 @@
+use crate::Index;
+
+fn index() -> Index {
+  Index::build(
+    vec![
+      ("services.nginx.enable", 0),
+      ("services.nginx.virtualHosts", 0),
+      ("services.nginx-unit.enable", 0),
+      ("services.tengine.enable", 0),
+      ("programs.git.enable", 0),
+      ("nginx", 1),
+    ]
+    .as_slice(),
+  )
+  .unwrap()
+}
+
+#[test]
+fn test_suggest() {
+  let index = index();
+
+  assert_eq!(
+    index.suggest(None, "servics.ngimx", 5).unwrap(),
+    vec!["services.nginx"]
+  );
+  assert_eq!(
+    index.suggest(None, "ngimx", 5).unwrap(),
+    vec!["nginx", "services.nginx"]
+  );
+  assert_eq!(
+    index.suggest(None, "ngimx.enabel", 5).unwrap(),
+    vec!["services.nginx.enable"]
+  );
+  assert_eq!(
+    index.suggest(None, "prgrams*enable", 5).unwrap(),
+    vec!["programs.git.enable"]
+  );
+}
+
+#[test]
+fn test_suggest_terms() {
+  let index = index();
+
+  // terms match in any order, negated ones are ignored
+  assert_eq!(
+    index.suggest(None, "enabel ngimx -unit", 5).unwrap(),
+    vec!["services.nginx.enable"]
+  );
+  // every term needs a close label
+  assert!(index.suggest(None, "ngimx apache", 5).unwrap().is_empty());
+  assert!(index.suggest(None, "scope:1", 5).unwrap().is_empty());
+
+  // the closest alternative counts, instead of all of their labels in a row
+  let mut suggestions = index.suggest(None, "ngimx|tengime", 5).unwrap();
+  suggestions.sort();
+  assert_eq!(suggestions, vec!["nginx", "services.nginx", "services.tengine"]);
+  let mut suggestions = index.suggest(None, "gti.enabel|ngimx.enabel", 5).unwrap();
+  suggestions.sort();
+  assert_eq!(suggestions, vec!["programs.git.enable", "services.nginx.enable"]);
+}
+
+#[test]
+fn test_suggest_limits() {
+  let index = index();
+
+  assert_eq!(index.suggest(None, "ngimx", 1).unwrap(), vec!["nginx"]);
+  assert!(index.suggest(None, "ngimx", 0).unwrap().is_empty());
+  assert!(index.suggest(None, "", 5).unwrap().is_empty());
+  assert!(index.suggest(None, "apache", 5).unwrap().is_empty());
+  assert!(index.suggest(None, "enable.services", 5).unwrap().is_empty());
+}
+
+#[test]
+fn test_suggest_scope() {
+  let index = index();
+
+  // `nginx` only exists in scope 1
+  assert_eq!(
+    index.suggest(Some(0), "ngimx", 5).unwrap(),
+    vec!["services.nginx"]
+  );
+  assert_eq!(index.suggest(Some(1), "ngimx", 5).unwrap(), vec!["nginx"]);
+  assert!(index.suggest(Some(2), "ngimx", 5).unwrap().is_empty());
+}
@@ -0,0 +1,264 @@
+use std::{borrow::Cow, collections::HashMap, sync::OnceLock};
+
+use crate::{
+  storage::Storage,
+  string_view::{ascii_ignore_case_find, fold_case, fuzzy_find, ignore_case_find, is_acronym, word_starts},
+};
+
+/// Posting lists from the (case folded) trigrams of the label table to the labels containing them.
+///
+/// Nothing of this is stored in the index file, it is built from the label table on first use.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Trigrams {
+  /// sorted and deduplicated label ids
+  postings: HashMap<[u8; 3], Vec<u32>>,
+  /// first letters of the words of every label, to find labels matching an acronym
+  initials: Vec<Box<[u8]>>,
+}
+
+impl Trigrams {
+  pub(crate) fn new<'a>(labels: impl Iterator<Item = &'a [u8]>) -> Self {
+    let mut postings = HashMap::<_, Vec<u32>>::new();
+    let mut initials = Vec::new();
+
+    for (idx, label) in labels.enumerate() {
+      initials.push(word_starts(label).map(|idx| label[idx]).collect());
+
+      for trigram in fold(label).windows(3) {
+        let ids = postings.entry(lowercase(trigram)).or_default();
+        // labels are visited in order, so this keeps the lists sorted and free of duplicates
+        if ids.last() != Some(&(idx as u32)) {
+          ids.push(idx as u32);
+        }
+      }
+    }
+
+    postings.shrink_to_fit();
+
+    Self { postings, initials }
+  }
+
+  /// Marks every label which contains `needle` or whose words start with its letters, ignoring case.
+  pub(crate) fn matching_labels(&self, storage: &impl Storage, needle: &[u8]) -> Vec<bool> {
+    let mut matching = self.substring_matching_labels(storage, needle);
+    self.mark_acronyms(needle, &mut matching);
+    matching
+  }
+
+  fn substring_matching_labels(&self, storage: &impl Storage, needle: &[u8]) -> Vec<bool> {
+    let mut matching = vec![false; storage.label_count()];
+    let folded = fold(needle);
+
+    if folded.len() < 3 {
+      for (idx, label) in storage.labels().enumerate() {
+        matching[idx] = ignore_case_find(label, needle).is_some();
+      }
+      return matching;
+    }
+
+    // start with the rarest trigram, so the intersection stays small
+    let mut lists = Vec::with_capacity(folded.len() - 2);
+    for trigram in folded.windows(3) {
+      match self.postings.get(&lowercase(trigram)) {
+        Some(ids) => lists.push(ids.as_slice()),
+        None => return matching,
+      }
+    }
+    lists.sort_by_key(|ids| ids.len());
+
+    'candidates: for idx in lists[0] {
+      for ids in &lists[1..] {
+        if ids.binary_search(idx).is_err() {
+          continue 'candidates;
+        }
+      }
+
+      // all trigrams are present, but not necessarily in the right order
+      let idx = *idx as usize;
+      matching[idx] = storage
+        .label(idx)
+        .is_some_and(|label| ignore_case_find(label, needle).is_some());
+    }
+
+    matching
+  }
+
+  /// Marks every label which contains `needle` with at most `max_distance` typos, see
+  /// [`fuzzy_find`].
+  pub(crate) fn fuzzy_matching_labels(
+    &self,
+    storage: &impl Storage,
+    needle: &[u8],
+    max_distance: usize,
+  ) -> Vec<bool> {
+    if max_distance == 0 {
+      return self.matching_labels(storage, needle);
+    }
+
+    let mut matching = vec![false; storage.label_count()];
+
+    // every typo changes at most four trigrams of the needle (swapping two bytes), so matching labels
+    // still contain the rest of them
+    let folded = fold(needle);
+    let required = folded.len().saturating_sub(2).saturating_sub(4 * max_distance);
+    if required == 0 {
+      for (idx, label) in storage.labels().enumerate() {
+        matching[idx] = fuzzy_find(label, needle, max_distance).is_some();
+      }
+      self.mark_acronyms(needle, &mut matching);
+      return matching;
+    }
+
+    let mut counts = vec![0u16; storage.label_count()];
+    for trigram in folded.windows(3) {
+      for idx in self.postings.get(&lowercase(trigram)).into_iter().flatten() {
+        counts[*idx as usize] = counts[*idx as usize].saturating_add(1);
+      }
+    }
+
+    for (idx, count) in counts.into_iter().enumerate() {
+      if usize::from(count) >= required {
+        matching[idx] = storage
+          .label(idx)
+          .is_some_and(|label| fuzzy_find(label, needle, max_distance).is_some());
+      }
+    }
+
+    self.mark_acronyms(needle, &mut matching);
+    matching
+  }
+
+  /// Marks the labels whose consecutive words start with the letters of `needle`, a superset of the
+  /// ones [`crate::string_view::acronym_find`] finds it in.
+  fn mark_acronyms(&self, needle: &[u8], matching: &mut [bool]) {
+    if !is_acronym(needle) {
+      return;
+    }
+
+    for (matching, initials) in matching.iter_mut().zip(&self.initials) {
+      *matching |= ascii_ignore_case_find(initials, needle).is_some();
+    }
+  }
+}
+
+/// Lazily built [`Trigrams`], which are never part of comparisons as they only mirror the label table.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct LazyTrigrams(OnceLock<Trigrams>);
+
+impl LazyTrigrams {
+  pub(crate) fn get_or_init(&self, init: impl FnOnce() -> Trigrams) -> &Trigrams {
+    self.0.get_or_init(init)
+  }
+}
+
+impl PartialEq for LazyTrigrams {
+  fn eq(&self, _other: &Self) -> bool {
+    true
+  }
+}
+
+/// Case folds labels and needles with non-ASCII chars up front, ASCII is lowercased per trigram.
+fn fold(bytes: &[u8]) -> Cow<'_, [u8]> {
+  if bytes.is_ascii() {
+    return Cow::Borrowed(bytes);
+  }
+
+  match std::str::from_utf8(bytes) {
+    Ok(str) => Cow::Owned(str.chars().map(fold_case).collect::<String>().into_bytes()),
+    Err(_) => Cow::Borrowed(bytes),
+  }
+}
+
+fn lowercase(trigram: &[u8]) -> [u8; 3] {
+  [
+    trigram[0].to_ascii_lowercase(),
+    trigram[1].to_ascii_lowercase(),
+    trigram[2].to_ascii_lowercase(),
+  ]
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::Index;
+
+  #[test]
+  fn matching_labels() {
+    let index = Index::new(
+      vec![
+        "services".into(),
+        "nginx".into(),
+        "enableNixpkgsReleaseCheck".into(),
+        "nginx-unit".into(),
+        "xngi".into(),
+        "ingn".into(),
+        "abcd-bcde".into(),
+      ],
+      vec![],
+    );
+    let trigrams = Trigrams::new(index.labels());
+
+    assert_eq!(
+      trigrams.matching_labels(&index, b"nginx"),
+      vec![false, true, false, true, false, false, false]
+    );
+    assert_eq!(
+      trigrams.matching_labels(&index, b"NIX"),
+      vec![false, false, true, false, false, false, false]
+    );
+    // "abcd-bcde" contains all trigrams of "abcde", but not in one piece
+    assert_eq!(
+      trigrams.matching_labels(&index, b"abcde"),
+      vec![false, false, false, false, false, false, false]
+    );
+    assert_eq!(
+      trigrams.matching_labels(&index, b"ng"),
+      vec![false, true, false, true, true, true, false]
+    );
+    assert_eq!(
+      trigrams.matching_labels(&index, b"zzz"),
+      vec![false, false, false, false, false, false, false]
+    );
+    // acronyms of words
+    assert_eq!(
+      trigrams.matching_labels(&index, b"nrc"),
+      vec![false, false, true, false, false, false, false]
+    );
+    assert_eq!(
+      trigrams.matching_labels(&index, b"nU"),
+      vec![false, false, false, true, false, false, false]
+    );
+  }
+
+  #[test]
+  fn fuzzy_matching_labels() {
+    let index = Index::new(
+      vec![
+        "services".into(),
+        "nginx".into(),
+        "enableNixpkgsReleaseCheck".into(),
+        "nginx-unit".into(),
+      ],
+      vec![],
+    );
+    let trigrams = Trigrams::new(index.labels());
+
+    assert_eq!(
+      trigrams.fuzzy_matching_labels(&index, b"nignx", 1),
+      vec![false, true, false, true]
+    );
+    assert_eq!(
+      trigrams.fuzzy_matching_labels(&index, b"nignx", 0),
+      vec![false, false, false, false]
+    );
+    // long enough to only verify labels sharing trigrams with it
+    assert_eq!(
+      trigrams.fuzzy_matching_labels(&index, b"nixpkgsreleasecheckk", 2),
+      vec![false, false, true, false]
+    );
+    assert_eq!(
+      trigrams.fuzzy_matching_labels(&index, b"nixpkgsrelaesecehck", 2),
+      vec![false, false, true, false]
+    );
+  }
+}
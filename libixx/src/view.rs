@@ -0,0 +1,489 @@
+use std::borrow::Cow;
+
+use crate::{
+  EntityKind, IxxError, Metadata, SearchHit, SearchOptions, SearchPage,
+  index::{LabelReference, decompress},
+  lookup::{self, Child, Lookup},
+  metadata::FORMAT_VERSION,
+  search::{self, SearchIter},
+  storage::Storage,
+  suggest,
+  trigram::{LazyTrigrams, Trigrams},
+};
+
+/// Read only index which resolves labels directly from the decompressed index file.
+///
+/// Unlike [`crate::Index`], which allocates every label and entry on its own, this validates the buffer
+/// once and only keeps offsets into it.
+#[derive(Debug, Clone)]
+pub struct IndexView<'a> {
+  buf: Cow<'a, [u8]>,
+  metadata: Metadata,
+  /// start and end of every label in `buf`
+  labels: Vec<(u32, u32)>,
+  /// scope id and the start and end of its labels in `references` for every entry
+  entries: Vec<(u32, u32, u32)>,
+  references: Vec<LabelReference>,
+  lookup: Lookup,
+  trigrams: LazyTrigrams,
+}
+
+impl<'a> IndexView<'a> {
+  /// Validates and indexes an already decompressed index file.
+  pub fn new(buf: impl Into<Cow<'a, [u8]>>) -> Result<Self, IxxError> {
+    let buf = buf.into();
+    let mut reader = Reader { buf: &buf, pos: 0 };
+
+    let metadata = match reader.bytes(5)? {
+      b"ixx02" => Metadata::legacy(),
+      b"ixx03" => reader.metadata(FORMAT_VERSION)?,
+      _ => return Err(IxxError::MalformedIndex("unknown magic")),
+    };
+    let format_version = metadata.format_version;
+
+    let label_count = reader.u32()?;
+    let mut labels = Vec::with_capacity((label_count as usize).min(buf.len()));
+    for _ in 0..label_count {
+      let len = reader.length(format_version)?;
+      let start = reader.pos;
+      std::str::from_utf8(reader.bytes(len)?)?;
+      labels.push((offset(start)?, offset(reader.pos)?));
+    }
+
+    let entry_count = reader.u32()?;
+    let mut entries = Vec::with_capacity((entry_count as usize).min(buf.len()));
+    let mut references = Vec::new();
+    for _ in 0..entry_count {
+      let scope_id = reader.scope_id(format_version)?;
+      let entry_label_count = reader.length(format_version)?;
+      let start = offset(references.len())?;
+      for _ in 0..entry_label_count {
+        let reference = reader.label_reference()?;
+        if reference.0 >= u64::from(label_count) {
+          return Err(IxxError::InvalidLabelReference);
+        }
+        references.push(reference);
+      }
+      entries.push((scope_id, start, offset(references.len())?));
+    }
+
+    if reader.pos != buf.len() {
+      return Err(IxxError::MalformedIndex("trailing data"));
+    }
+
+    let mut view = Self {
+      buf,
+      metadata,
+      labels,
+      entries,
+      references,
+      lookup: Lookup::default(),
+      trigrams: LazyTrigrams::default(),
+    };
+    view.lookup = Lookup::new(view.labels(), Storage::entries(&view));
+
+    Ok(view)
+  }
+
+  /// Decompresses an index file and keeps the result as the backing buffer.
+  pub fn read(buf: &[u8]) -> Result<IndexView<'static>, IxxError> {
+    IndexView::new(decompress(buf)?)
+  }
+
+  #[must_use]
+  pub fn metadata(&self) -> &Metadata {
+    &self.metadata
+  }
+
+  pub fn get_idx_by_name(&self, scope_id: u32, name: &str) -> Option<usize> {
+    lookup::get_idx_by_name(self, scope_id, name)
+  }
+
+  /// Resolves a path from a configuration to the entry declaring it, treating placeholder labels like
+  /// `<name>` or `*` as wildcards.
+  pub fn get_idx_by_config_path(&self, scope_id: u32, path: &str) -> Option<usize> {
+    lookup::get_idx_by_config_path(self, scope_id, path)
+  }
+
+  /// Returns the name of the entry at `idx`, as returned by searches.
+  pub fn name_of(&self, idx: usize) -> Option<String> {
+    lookup::name_of(self, idx)
+  }
+
+  pub fn scope_of(&self, idx: usize) -> Option<u32> {
+    Some(self.entry(idx)?.0)
+  }
+
+  /// Iterates over the index, scope id and name of every entry.
+  pub fn entries(&self) -> impl Iterator<Item = (usize, u32, String)> {
+    lookup::entries(self)
+  }
+
+  /// Lists the distinct labels directly below the attribute path `prefix`, or the first labels of all
+  /// names if it is empty, ordered by label.
+  pub fn children(&self, scope_id: u32, prefix: &str) -> Vec<Child> {
+    lookup::children(self, scope_id, prefix)
+  }
+
+  /// Completes the last label of a partially typed attribute path like `services.ngi` to up to
+  /// `max_results` existing paths, like `services.nginx`, most frequent labels first.
+  pub fn complete(&self, scope_id: u32, partial: &str, max_results: usize) -> Vec<String> {
+    lookup::complete(self, scope_id, partial, max_results)
+  }
+
+  pub fn search(
+    &self,
+    scope_id: Option<u32>,
+    query: &str,
+    max_results: usize,
+  ) -> Result<Vec<SearchHit>, IxxError> {
+    self.search_with(scope_id, query, max_results, &SearchOptions::default())
+  }
+
+  pub fn search_with(
+    &self,
+    scope_id: Option<u32>,
+    query: &str,
+    max_results: usize,
+    options: &SearchOptions,
+  ) -> Result<Vec<SearchHit>, IxxError> {
+    Ok(self.search_page(scope_id, query, 0, max_results, options)?.hits)
+  }
+
+  /// Skips the best `offset` hits and also counts all names matching the query, to page through the
+  /// results.
+  pub fn search_page(
+    &self,
+    scope_id: Option<u32>,
+    query: &str,
+    offset: usize,
+    max_results: usize,
+    options: &SearchOptions,
+  ) -> Result<SearchPage, IxxError> {
+    search::search(self, scope_id, query, offset, max_results, options)
+  }
+
+  /// Lazily yields the hits for `query` in index order instead of ranking them, to filter them further
+  /// or stop early without checking every entry.
+  pub fn search_iter<'s>(
+    &'s self,
+    scope_id: Option<u32>,
+    query: &'s str,
+    options: &SearchOptions,
+  ) -> Result<impl Iterator<Item = Result<SearchHit, IxxError>> + use<'s, 'a>, IxxError> {
+    SearchIter::new(self, scope_id, query, options)
+  }
+
+  /// Finds names matching the regular expression `pattern`, see [`regex::Regex`] for the syntax.
+  #[cfg(feature = "regex")]
+  pub fn search_regex(
+    &self,
+    scope_id: Option<u32>,
+    pattern: &str,
+    max_results: usize,
+  ) -> Result<Vec<SearchHit>, IxxError> {
+    Ok(self.search_regex_page(scope_id, pattern, 0, max_results)?.hits)
+  }
+
+  /// Like [`Self::search_regex`], but skips the best `offset` hits and counts all matching names.
+  #[cfg(feature = "regex")]
+  pub fn search_regex_page(
+    &self,
+    scope_id: Option<u32>,
+    pattern: &str,
+    offset: usize,
+    max_results: usize,
+  ) -> Result<SearchPage, IxxError> {
+    crate::regex_search::search_regex(self, scope_id, pattern, offset, max_results)
+  }
+
+  /// Proposes up to `max_results` existing names close to `query`, for when searching finds nothing.
+  ///
+  /// Only names in `scope_id` are proposed, if it is given.
+  pub fn suggest(
+    &self,
+    scope_id: Option<u32>,
+    query: &str,
+    max_results: usize,
+  ) -> Result<Vec<String>, IxxError> {
+    suggest::suggest(self, scope_id, query, max_results)
+  }
+
+  #[must_use]
+  pub fn size(&self) -> usize {
+    self.entries.len()
+  }
+}
+
+impl Storage for IndexView<'_> {
+  fn label_count(&self) -> usize {
+    self.labels.len()
+  }
+
+  fn label(&self, idx: usize) -> Option<&[u8]> {
+    let (start, end) = *self.labels.get(idx)?;
+    self.buf.get(start as usize..end as usize)
+  }
+
+  fn entry_count(&self) -> usize {
+    self.entries.len()
+  }
+
+  fn entry(&self, idx: usize) -> Option<(u32, &[LabelReference])> {
+    let (scope_id, start, end) = *self.entries.get(idx)?;
+    Some((scope_id, self.references.get(start as usize..end as usize)?))
+  }
+
+  fn metadata(&self) -> &Metadata {
+    &self.metadata
+  }
+
+  fn lookup(&self) -> &Lookup {
+    &self.lookup
+  }
+
+  fn trigrams(&self) -> &Trigrams {
+    self.trigrams.get_or_init(|| Trigrams::new(self.labels()))
+  }
+}
+
+/// Positions are kept as `u32` to save memory, larger buffers are rejected.
+fn offset(pos: usize) -> Result<u32, IxxError> {
+  u32::try_from(pos).map_err(|_| IxxError::MalformedIndex("index too large"))
+}
+
+/// Little endian reader over the decompressed index, mirroring the binrw definitions in `index.rs`.
+struct Reader<'a> {
+  buf: &'a [u8],
+  pos: usize,
+}
+
+impl<'a> Reader<'a> {
+  fn bytes(&mut self, len: usize) -> Result<&'a [u8], IxxError> {
+    let end = self
+      .pos
+      .checked_add(len)
+      .ok_or(IxxError::MalformedIndex("length out of range"))?;
+    let bytes = self
+      .buf
+      .get(self.pos..end)
+      .ok_or(IxxError::MalformedIndex("unexpected end of data"))?;
+    self.pos = end;
+    Ok(bytes)
+  }
+
+  fn array<const N: usize>(&mut self) -> Result<[u8; N], IxxError> {
+    Ok(self.bytes(N)?.try_into().expect("exactly N bytes were taken"))
+  }
+
+  fn u8(&mut self) -> Result<u8, IxxError> {
+    Ok(u8::from_le_bytes(self.array()?))
+  }
+
+  fn u32(&mut self) -> Result<u32, IxxError> {
+    Ok(u32::from_le_bytes(self.array()?))
+  }
+
+  fn u64(&mut self) -> Result<u64, IxxError> {
+    Ok(u64::from_le_bytes(self.array()?))
+  }
+
+  /// Lengths are a single byte in `ixx02` and a size tagged integer since `ixx03`.
+  fn length(&mut self, format_version: u8) -> Result<usize, IxxError> {
+    if format_version < 3 {
+      Ok(usize::from(self.u8()?))
+    } else {
+      usize::try_from(self.var_int()?).map_err(|_| IxxError::MalformedIndex("length out of range"))
+    }
+  }
+
+  fn string(&mut self, format_version: u8) -> Result<String, IxxError> {
+    let len = self.length(format_version)?;
+    Ok(std::str::from_utf8(self.bytes(len)?)?.to_string())
+  }
+
+  fn metadata(&mut self, format_version: u8) -> Result<Metadata, IxxError> {
+    let chunk_size = self.u32()?;
+    let kind = match self.u8()? {
+      0 => EntityKind::Unknown,
+      1 => EntityKind::Options,
+      2 => EntityKind::Packages,
+      _ => return Err(IxxError::MalformedIndex("unknown entity kind")),
+    };
+    let build_timestamp = self.u64()?;
+    let generator = self.string(format_version)?;
+    let scope_count = self.var_int()?;
+    let scopes = (0..scope_count)
+      .map(|_| self.string(format_version))
+      .collect::<Result<_, _>>()?;
+
+    Ok(Metadata {
+      format_version,
+      chunk_size,
+      kind,
+      build_timestamp,
+      generator,
+      scopes,
+    })
+  }
+
+  fn var_int(&mut self) -> Result<u64, IxxError> {
+    Ok(match self.u8()? {
+      0 => u64::from(self.u8()?),
+      1 => u64::from(u16::from_le_bytes(self.array()?)),
+      2 => u64::from(self.u32()?),
+      3 => self.u64()?,
+      _ => return Err(IxxError::MalformedIndex("invalid integer size")),
+    })
+  }
+
+  /// Scope ids are a single byte in `ixx02` and a size tagged integer since `ixx03`.
+  fn scope_id(&mut self, format_version: u8) -> Result<u32, IxxError> {
+    if format_version < 3 {
+      Ok(u32::from(self.u8()?))
+    } else {
+      u32::try_from(self.var_int()?).map_err(|_| IxxError::MalformedIndex("scope id out of range"))
+    }
+  }
+
+  fn label_reference(&mut self) -> Result<LabelReference, IxxError> {
+    Ok(LabelReference(self.var_int()?))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::Cursor;
+
+  use super::*;
+  use crate::Index;
+
+  fn write(index: &Index) -> Vec<u8> {
+    let mut buf = Cursor::new(Vec::new());
+    index.write_into(&mut buf).unwrap();
+    buf.into_inner()
+  }
+
+  #[test]
+  fn matches_index() {
+    let index = Index::build(&[
+      ("home.enableDebugInfo", 0),
+      ("home.enableNixpkgsReleaseCheck", 0),
+      ("services.home-manager.autoUpgrade.enable", 0),
+      ("pretalx", 0),
+      ("nixosTests.pretalx", 0),
+      ("home.enableDebugInfo", 1),
+    ])
+    .unwrap();
+    let view = IndexView::read(&write(&index)).unwrap();
+
+    assert_eq!(view.size(), index.size());
+
+    for query in ["", "ho*en", "pretalx", "debug", "nonexistent"] {
+      assert_eq!(
+        view.search(None, query, 10).unwrap(),
+        index.search(None, query, 10).unwrap()
+      );
+      assert_eq!(
+        view
+          .search_iter(None, query, &SearchOptions::default())
+          .unwrap()
+          .collect::<Result<Vec<_>, _>>()
+          .unwrap(),
+        index
+          .search_iter(None, query, &SearchOptions::default())
+          .unwrap()
+          .collect::<Result<Vec<_>, _>>()
+          .unwrap()
+      );
+    }
+
+    assert!(view.entries().eq(index.entries()));
+    assert_eq!(view.name_of(4).as_deref(), Some("nixosTests.pretalx"));
+    assert_eq!(view.scope_of(5), Some(1));
+
+    assert_eq!(view.get_idx_by_name(0, "nixosTests.pretalx"), Some(4));
+    assert_eq!(view.get_idx_by_name(1, "home.enableDebugInfo"), Some(5));
+    assert_eq!(view.get_idx_by_name(1, "pretalx"), None);
+  }
+
+  #[test]
+  fn metadata() {
+    let metadata = Metadata {
+      chunk_size: 100,
+      kind: EntityKind::Options,
+      build_timestamp: 1_700_000_000,
+      generator: "ixx 1.2.3".to_string(),
+      scopes: vec!["NixOS".to_string(), "Home Manager".to_string()],
+      ..Metadata::default()
+    };
+    let index = Index::build(&[("foo.bar", 0)])
+      .unwrap()
+      .with_metadata(metadata.clone());
+    let view = IndexView::read(&write(&index)).unwrap();
+
+    assert_eq!(view.metadata(), &metadata);
+  }
+
+  #[test]
+  fn borrowed_buffer() {
+    let index = Index::build(&[("foo.bar", 0)]).unwrap();
+    let decompressed = decompress(&write(&index)).unwrap();
+    let view = IndexView::new(decompressed.as_slice()).unwrap();
+
+    assert_eq!(view.get_idx_by_name(0, "foo.bar"), Some(0));
+  }
+
+  #[test]
+  fn malformed() {
+    let index = Index::build(&[("foo.bar", 0), ("foo.baz", 1)]).unwrap();
+    let decompressed = decompress(&write(&index)).unwrap();
+
+    for len in 0..decompressed.len() {
+      assert!(IndexView::new(&decompressed[..len]).is_err());
+    }
+
+    let mut trailing = decompressed.clone();
+    trailing.push(0);
+    assert!(IndexView::new(trailing).is_err());
+
+    let mut magic = decompressed.clone();
+    magic[4] = b'9';
+    assert!(IndexView::new(magic).is_err());
+
+    // the last byte is the reference to "baz"
+    let mut reference = decompressed;
+    *reference.last_mut().unwrap() = 3;
+    assert!(matches!(
+      IndexView::new(reference),
+      Err(IxxError::InvalidLabelReference)
+    ));
+  }
+
+  #[test]
+  #[cfg(target_pointer_width = "64")]
+  fn offset_out_of_range() {
+    assert_eq!(offset(u32::MAX as usize).unwrap(), u32::MAX);
+    assert!(matches!(
+      offset(u32::MAX as usize + 1),
+      Err(IxxError::MalformedIndex(_))
+    ));
+  }
+
+  #[test]
+  fn huge_length() {
+    let mut buf = b"ixx03".to_vec();
+    // chunk size, kind, build timestamp, empty generator and no scopes
+    buf.extend_from_slice(&[0; 4]);
+    buf.push(0);
+    buf.extend_from_slice(&[0; 8]);
+    buf.extend_from_slice(&[0, 0, 0, 0]);
+    // a single label of u64::MAX bytes
+    buf.extend_from_slice(&1u32.to_le_bytes());
+    buf.push(3);
+    buf.extend_from_slice(&u64::MAX.to_le_bytes());
+    buf.extend_from_slice(b"foo");
+
+    assert!(matches!(IndexView::new(buf), Err(IxxError::MalformedIndex(_))));
+  }
+}